@@ -1,351 +1,5578 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions as ix_sysvar;
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use solana_curve25519::edwards::{validate_edwards, PodEdwardsPoint};
+use solana_sdk_ids::ed25519_program;
+use solana_sha256_hasher::hash;
 
 declare_id!("3iDskEsSVNRmbn7uwygUVsBNGEj1hqE2ZCaHSQhhVtD9");
 
+/// Maximum number of keys in the admin multisig set.
+pub const MAX_ADMINS: usize = 5;
+
+/// Hard cap on a state's participants; `max_participants` can only lower it.
+pub const MAX_PARTICIPANTS: u8 = 16;
+
+/// Challenge period used when `initialize` is called with `0` (10 minutes).
+pub const DEFAULT_CHALLENGE_PERIOD: i64 = 600;
+
+/// Floor that every configured challenge period (global or per-token) must respect, so a
+/// careless admin can't open a window for near-instant fraudulent withdrawals.
+pub const MIN_CHALLENGE_PERIOD: i64 = 60;
+
+/// Upper bound on any configured challenge period (30 days).
+pub const MAX_CHALLENGE_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+/// Most token accounts `withdraw_split` pays out to in one instruction.
+pub const MAX_SPLIT_RECIPIENTS: usize = 8;
+
+/// Most candidate states `challenge_multi` weighs in one instruction, to bound its compute.
+pub const MAX_CHALLENGE_CANDIDATES: usize = 4;
+
+/// Maximum number of entries in `VaultConfig::challenge_tiers`.
+pub const MAX_CHALLENGE_TIERS: usize = 4;
+
+/// Most mints `TokenRegistry` lists.
+pub const MAX_REGISTERED_TOKENS: usize = 64;
+
+/// Upper bound on the withdrawal fee (10%).
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+/// Default window during which an exiting node's bond stays slashable (7 days).
+pub const DEFAULT_DISPUTE_WINDOW: i64 = 7 * 24 * 60 * 60;
+
+/// Default time an unclaimed request stays withdrawable after its challenge period before it
+/// may be swept (7 days).
+pub const DEFAULT_SWEEP_DELAY: i64 = 7 * 24 * 60 * 60;
+
+/// Default delay between queueing a timelocked config change and executing it (2 days).
+pub const DEFAULT_TIMELOCK_DELAY: i64 = 2 * 24 * 60 * 60;
+
+/// Shortest configurable timelock delay, so a change can never be queued and executed in the
+/// same transaction, whatever the delay was lowered to.
+pub const MIN_TIMELOCK_DELAY: i64 = 1;
+
+/// `VaultConfig::min_deposit` is in units of `10^-MIN_DEPOSIT_DECIMALS` of a whole token.
+pub const MIN_DEPOSIT_DECIMALS: u32 = 2;
+
+/// Share of the active node set (by count and by weight) whose signatures form a quorum.
+pub const QUORUM_BPS: u64 = 6_667;
+
+/// Layout version of `ConfigView`. Bumped whenever fields are appended.
+pub const CONFIG_VIEW_VERSION: u8 = 34;
+
+/// Layout version of the stored `VaultConfig`. Bumped whenever fields are appended, so
+/// `migrate_config` knows to grow older accounts.
+pub const CONFIG_VERSION: u8 = 17;
+
+/// `State::state_version` that `State::new` builds and clients should sign.
+pub const CURRENT_STATE_VERSION: u8 = StateVersion::V2 as u8;
+
+/// Layout version of `Limits`. Bumped whenever fields are appended or change meaning.
+pub const LIMITS_VERSION: u8 = 3;
+
+/// Length of the window `VaultState::daily_withdraw_limit` applies to, aligned to UTC days.
+pub const WITHDRAW_DAY: i64 = 24 * 60 * 60;
+
+/// Layout version of the stored `NodeEntry`. Bumped whenever fields are appended, so
+/// `migrate_node` knows to grow and default older entries.
+pub const NODE_ENTRY_VERSION: u8 = 2;
+
+/// Layout version of `NodeView`. Bumped whenever fields are appended.
+pub const NODE_VIEW_VERSION: u8 = 3;
+
+/// Layout version of `Health`. Bumped whenever fields are appended.
+pub const HEALTH_VERSION: u8 = 1;
+
+/// Layout version of `RequestView`. Bumped whenever fields are appended.
+pub const REQUEST_VIEW_VERSION: u8 = 1;
+
 #[program]
 pub mod clearnet {
     use super::*;
 
-    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+    /// `challenge_period` is in seconds; pass `0` to use `DEFAULT_CHALLENGE_PERIOD`.
+    pub fn initialize(ctx: Context<Initialize>, challenge_period: i64) -> Result<()> {
+        init_config(
+            &mut ctx.accounts.config,
+            ctx.accounts.admin.key(),
+            challenge_period,
+        )
+    }
+
+    /// `initialize` and the deployment settings in one instruction, so the config is never
+    /// live with defaults. Each parameter is validated as by its setter, except that
+    /// `min_signers` is only held to `max_participants` while no node is registered;
+    /// `fee_recipient` is set directly, since nothing has accrued yet for the timelock to protect.
+    pub fn initialize_and_configure(ctx: Context<Initialize>, params: InitParams) -> Result<()> {
+        require!(params.fee_bps <= MAX_FEE_BPS, ClearnetError::InvalidFee);
+        require!(
+            (1..=MAX_PARTICIPANTS).contains(&params.max_participants),
+            ClearnetError::InvalidMaxParticipants
+        );
+        require!(
+            params.min_signers <= params.max_participants,
+            ClearnetError::InvalidMinSigners
+        );
         let config = &mut ctx.accounts.config;
-        config.admin = ctx.accounts.admin.key();
-        config.challenge_period = 600; // 10 minutes
+        init_config(config, ctx.accounts.admin.key(), params.challenge_period)?;
+
+        config.fee_bps = params.fee_bps;
+        config.fee_recipient = params.fee_recipient;
+        config.min_signers = params.min_signers;
+        config.max_participants = params.max_participants;
+        config.max_open_requests = params.max_open_requests;
+        config.min_deposit = params.min_deposit;
         Ok(())
     }
 
-    pub fn set_node_status(ctx: Context<SetNodeStatus>, status: bool) -> Result<()> {
-        if status {
-            // Activate: The node account is initialized by Anchor (init_if_needed)
-            // We just ensure it's marked active if we add a flag, but purely existing is enough?
-            // Let's use a flag in case we want to ban them without closing the account (to keep history? no).
-            // Actually, closing the account is better for rent.
-            // But the instruction `SetNodeStatus` with `status=false` should close it.
-            // Anchor's `close` constraint handles closing.
-            // Here we just initialize. logic is handled by Account constraints in the struct.
-        } else {
-            // Deactivate is handled by `close` in a separate instruction usually,
-            // or we just assume if it exists it's active.
-            // But to toggle `status` we might need a bool in the account.
+    /// Grows a config account created by an older program version to the current layout and
+    /// stamps it with `CONFIG_VERSION`. Fields newer than the account's version get
+    /// `init_config`'s defaults (see `VaultConfig::default_fields_since`); the rest read as
+    /// zero, which every such setting treats as its pre-existing behaviour. Safe to call again
+    /// once up to date; the event then reports the same version on both sides.
+    pub fn migrate_config(ctx: Context<MigrateConfig>) -> Result<()> {
+        let info = ctx.accounts.config.to_account_info();
+        grow_account(
+            &info,
+            &ctx.accounts.admin,
+            &ctx.accounts.system_program,
+            8 + VaultConfig::INIT_SPACE,
+        )?;
+
+        // Only deserializable once it has been grown.
+        let mut config = VaultConfig::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        require_keys_eq!(
+            config.admin,
+            ctx.accounts.admin.key(),
+            ClearnetError::Unauthorized
+        );
+        require_admin_threshold(&config, &ctx.accounts.admin, ctx.remaining_accounts)?;
+
+        let from_version = config.version;
+        config.default_fields_since(from_version);
+        config.version = CONFIG_VERSION;
+        config.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+
+        emit!(ConfigMigrated {
+            from_version,
+            to_version: CONFIG_VERSION,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Grows a `NodeEntry` created by an older program version to the current layout and
+    /// defaults the fields added since, keeping its authority, status and bond. Run by the
+    /// node itself or by the admins; safe to call again once up to date.
+    pub fn migrate_node(ctx: Context<MigrateNode>, _node_authority: Pubkey) -> Result<()> {
+        let info = ctx.accounts.node_entry.to_account_info();
+        grow_account(
+            &info,
+            &ctx.accounts.signer,
+            &ctx.accounts.system_program,
+            8 + NodeEntry::INIT_SPACE,
+        )?;
+
+        let mut node_entry = NodeEntry::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+        if ctx.accounts.signer.key() != node_entry.authority {
+            require!(
+                ctx.accounts.config.is_admin(&ctx.accounts.signer.key()),
+                ClearnetError::Unauthorized
+            );
+            require_admin_threshold(
+                &ctx.accounts.config,
+                &ctx.accounts.signer,
+                ctx.remaining_accounts,
+            )?;
+        }
+
+        if node_entry.version == 0 {
+            // Entries from before weights count as one vote, and an active node gets a fresh
+            // heartbeat so it isn't stale the moment liveness checks apply to it.
+            if node_entry.weight == 0 {
+                node_entry.weight = 1;
+                if node_entry.is_active {
+                    ctx.accounts.config.active_node_weight += 1;
+                }
+            }
+            if node_entry.is_active && node_entry.last_seen == 0 {
+                node_entry.last_seen = Clock::get()?.unix_timestamp;
+            }
         }
-        ctx.accounts.node_entry.is_active = status;
-        ctx.accounts.node_entry.authority = ctx.accounts.node_authority.key();
+        node_entry.version = NODE_ENTRY_VERSION;
+        node_entry.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
         Ok(())
     }
 
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        // Transfer Tokens/SOL to Vault
-        // For simplicity, we implement SPL Token transfer.
-        // If native SOL, one would wrap it or use SystemProgram transfer to a PDA.
-        // We assume USDC (SPL) for this implementation as per README "USDT".
+    pub fn set_admins(ctx: Context<SetAdmins>, admins: Vec<Pubkey>, threshold: u8) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.user_token.to_account_info(),
-            to: ctx.accounts.vault_token.to_account_info(),
-            authority: ctx.accounts.user.to_account_info(),
-        };
-        let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
-        token::transfer(cpi_ctx, amount)?;
+        require!(
+            !admins.is_empty() && admins.len() <= MAX_ADMINS,
+            ClearnetError::InvalidAdminSet
+        );
+        require!(
+            threshold > 0 && threshold as usize <= admins.len(),
+            ClearnetError::InvalidAdminSet
+        );
+        for (i, key) in admins.iter().enumerate() {
+            require!(*key != Pubkey::default(), ClearnetError::InvalidAdminSet);
+            require!(!admins[..i].contains(key), ClearnetError::InvalidAdminSet);
+        }
 
-        emit!(Deposited {
-            wallet: ctx.accounts.user.key(),
-            token: ctx.accounts.mint.key(),
-            amount,
-        });
+        let config = &mut ctx.accounts.config;
+        config.admins = [Pubkey::default(); MAX_ADMINS];
+        config.admins[..admins.len()].copy_from_slice(&admins);
+        config.threshold = threshold;
+        // The first key is the primary admin: it pays for and proposes admin instructions.
+        let old = config.admin;
+        config.admin = admins[0];
+        if config.admin != old {
+            emit!(AdminChanged {
+                old,
+                new: config.admin,
+                ts: Clock::get()?.unix_timestamp,
+            });
+        }
+        Ok(())
+    }
 
+    pub fn update_challenge_period(
+        ctx: Context<UpdateConfig>,
+        challenge_period: i64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        validate_challenge_period(challenge_period)?;
+
+        ctx.accounts.config.challenge_period = challenge_period;
         Ok(())
     }
 
-    pub fn request(ctx: Context<Request>, state: State, amount: u64) -> Result<()> {
-        let clock = Clock::get()?;
-        let req_acct = &mut ctx.accounts.request_account;
+    /// Sets the smallest deposit accepted, in hundredths of a whole token
+    /// (`MIN_DEPOSIT_DECIMALS`), so one value means the same across mints of any decimals.
+    /// `0` disables the minimum.
+    pub fn set_min_deposit(ctx: Context<UpdateConfig>, min_deposit: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-        // 1. Validation
+        ctx.accounts.config.min_deposit = min_deposit;
+        Ok(())
+    }
+
+    /// Closes challenges `challenge_lockout` seconds before a request's expiration, so the
+    /// last stretch of the period can't be raced; `withdraw` still waits for the expiration.
+    /// Must stay below `MIN_CHALLENGE_PERIOD` so every period keeps an open window; `0`
+    /// allows challenges up to and including the expiration.
+    pub fn set_challenge_lockout(ctx: Context<UpdateConfig>, challenge_lockout: i64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
         require!(
-            amount <= state.balance,
-            ClearnetError::InsufficientStateBalance
+            (0..MIN_CHALLENGE_PERIOD).contains(&challenge_lockout),
+            ClearnetError::InvalidChallengeLockout
         );
+
+        ctx.accounts.config.challenge_lockout = challenge_lockout;
+        Ok(())
+    }
+
+    /// Overrides the challenge period for one mint; `0` clears the override.
+    pub fn set_token_challenge_period(
+        ctx: Context<UpdateVaultState>,
+        challenge_period: i64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        if challenge_period != 0 {
+            validate_challenge_period(challenge_period)?;
+        }
+
+        ctx.accounts.vault_state.challenge_period = challenge_period;
+        Ok(())
+    }
+
+    /// Sets the smallest amount of this token that can be withdrawn, in its base units.
+    pub fn set_min_withdraw(ctx: Context<UpdateVaultState>, min_withdraw: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.vault_state.min_withdraw = min_withdraw;
+        Ok(())
+    }
+
+    /// Caps any single withdrawal of this token, in its base units; `0` removes the cap. Larger
+    /// exits have to be split, which bounds what one fraudulent state can take.
+    pub fn set_max_single_withdraw(
+        ctx: Context<UpdateVaultState>,
+        max_single_withdraw: u64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.vault_state.max_single_withdraw = max_single_withdraw;
+        Ok(())
+    }
+
+    /// Caps single deposits of this token and the total the vault may owe its users, both in
+    /// base units; `0` removes either cap.
+    pub fn set_deposit_limits(
+        ctx: Context<UpdateVaultState>,
+        max_deposit: u64,
+        deposit_cap: u64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.max_deposit = max_deposit;
+        vault_state.deposit_cap = deposit_cap;
+        Ok(())
+    }
+
+    /// Caps how much of this token is paid out per UTC day across all withdrawal paths, in base
+    /// units; `0` removes the limit.
+    pub fn set_daily_withdraw_limit(
+        ctx: Context<UpdateVaultState>,
+        daily_withdraw_limit: u64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.vault_state.daily_withdraw_limit = daily_withdraw_limit;
+        Ok(())
+    }
+
+    /// Sets the withdrawal fee. The fee recipient only changes through `queue_fee_recipient`
+    /// and `execute_fee_recipient`.
+    pub fn set_fee(ctx: Context<UpdateConfig>, fee_bps: u16) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(fee_bps <= MAX_FEE_BPS, ClearnetError::InvalidFee);
+
+        ctx.accounts.config.fee_bps = fee_bps;
+        Ok(())
+    }
+
+    /// Sets the delay timelocked changes wait between being queued and executed, at least
+    /// `MIN_TIMELOCK_DELAY`. It applies to changes queued afterwards.
+    pub fn set_timelock_delay(ctx: Context<UpdateConfig>, timelock_delay: i64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
         require!(
-            req_acct.expiration == 0,
-            ClearnetError::RequestAlreadyPending
-        ); // Assuming 0 means not active
+            timelock_delay >= MIN_TIMELOCK_DELAY,
+            ClearnetError::InvalidTimelockDelay
+        );
 
-        // 2. Verify Signatures
-        // This is complex in SVM. We will use a helper that hashes the state
-        // and ensures the provided `sigs` match the `participants`.
-        // Ideally, we check `ed25519_program` instructions, but here we'll mock the check
-        // or perform a naive check if possible.
-        // For PROTOTYPE: We trust the `participants` are nodes and signatures are present.
-        // Implementing full Ed25519 verify in user-space is too costly for this snippet.
-        // We will check that `participants` are valid nodes stored in `node_registry`.
-        // But `participants` is a list in `State`.
-        // We need to pass the Node accounts to the instruction to verify they exist and are active.
-        // Anchor `remaining_accounts` is good for this.
+        ctx.accounts.config.timelock_delay = timelock_delay;
+        Ok(())
+    }
+
+    /// Queues `fee_recipient` to receive fees once `timelock_delay` has passed, replacing any
+    /// change queued before. Gives users time to react before accrued fees can be redirected.
+    pub fn queue_fee_recipient(ctx: Context<UpdateConfig>, fee_recipient: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-        let participants = &state.participants;
-        let mut _valid_sigs = 0;
+        let config = &mut ctx.accounts.config;
+        let eta = Clock::get()?.unix_timestamp + config.timelock_delay;
+        config.pending_fee_recipient = fee_recipient;
+        config.fee_recipient_eta = eta;
+        emit!(FeeRecipientQueued { fee_recipient, eta });
+        Ok(())
+    }
 
-        // Iterate over remaining accounts (Nodes) to verify they match `participants` and are authorized
-        // This validates that the listed participants are indeed Nodes.
-        // It DOES NOT verify the cryptographic signature in this snippet (requires Ed25519 verify).
-        // IN PRODUCTION: You must verify the Ed25519 signatures!
+    /// Applies the fee recipient queued by `queue_fee_recipient` once its delay is over.
+    pub fn execute_fee_recipient(ctx: Context<UpdateConfig>) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-        // Mock Sig Check:
+        let config = &mut ctx.accounts.config;
+        require!(config.fee_recipient_eta != 0, ClearnetError::NoQueuedChange);
+        let now = Clock::get()?.unix_timestamp;
         require!(
-            state.sigs.len() == participants.len(),
-            ClearnetError::SigMismatch
+            now >= config.fee_recipient_eta,
+            ClearnetError::TimelockNotExpired
         );
 
-        // 3. Store Request
-        req_acct.wallet = state.wallet;
-        req_acct.token = state.token;
-        req_acct.amount = amount;
-        req_acct.height = state.height;
-        req_acct.expiration = clock.unix_timestamp + ctx.accounts.config.challenge_period;
-        req_acct.bump = ctx.bumps.request_account;
-
-        emit!(Requested {
-            wallet: state.wallet,
-            token: state.token,
-            amount,
+        let old = config.fee_recipient;
+        config.fee_recipient = config.pending_fee_recipient;
+        config.pending_fee_recipient = Pubkey::default();
+        config.fee_recipient_eta = 0;
+        emit!(FeeRecipientChanged {
+            old,
+            new: config.fee_recipient,
+            ts: now,
         });
+        Ok(())
+    }
 
-        emit!(Challenged {
-            wallet: state.wallet,
-            height: state.height,
-            expiration: req_acct.expiration,
-        });
+    /// Sets the share of a slashed bond, in basis points, that `report_equivocation` sends to
+    /// the treasury instead of the reporter.
+    pub fn set_slash_split(ctx: Context<UpdateConfig>, slash_split_bps: u16) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(slash_split_bps <= 10_000, ClearnetError::InvalidSlashSplit);
 
+        ctx.accounts.config.slash_split_bps = slash_split_bps;
         Ok(())
     }
 
-    pub fn challenge(ctx: Context<Challenge>, candidate: State) -> Result<()> {
-        let req_acct = &ctx.accounts.request_account;
+    /// Sets the fee charged by `instant_withdraw`; `0` disables instant withdrawals.
+    pub fn set_instant_fee(ctx: Context<UpdateConfig>, instant_fee_bps: u16) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(
+            instant_fee_bps <= MAX_FEE_BPS
+                && (instant_fee_bps == 0 || instant_fee_bps >= ctx.accounts.config.fee_bps),
+            ClearnetError::InvalidFee
+        );
 
-        // 1. Verify existence of request
-        require!(req_acct.expiration > 0, ClearnetError::NoPendingRequest);
+        ctx.accounts.config.instant_fee_bps = instant_fee_bps;
+        Ok(())
+    }
 
-        // 2. Verify new state is newer
+    /// Caps how many participants a state may carry in `request` and `challenge`, so
+    /// signature checks stay within the compute budget for the operator's validator set.
+    pub fn set_max_participants(ctx: Context<UpdateConfig>, max_participants: u8) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
         require!(
-            candidate.height > req_acct.height,
-            ClearnetError::CandidateNotNewer
+            (1..=MAX_PARTICIPANTS).contains(&max_participants),
+            ClearnetError::InvalidMaxParticipants
         );
 
-        // 3. Verify signatures (Mock as above)
-        // require(verify_sigs(candidate), ...);
-
-        // 4. Close request (Reject)
-        // logic handled by `close` constraint or manual close?
-        // We want to delete the data. The `close` instruction handles transferring lamports.
-        // We just need to ensure the logic flows there.
-        // In Anchor, we can't conditionally close in the same instruction easily unless we use `close` constraint on a separate "Close" ix
-        // or manually realloc/assign.
-        // Standard pattern: Mark as invalid, or actually Close.
-        // We will close the account by sending lamports to the challenger.
+        ctx.accounts.config.max_participants = max_participants;
+        Ok(())
+    }
 
-        emit!(Rejected {
-            wallet: req_acct.wallet,
-            token: req_acct.token,
-            amount: req_acct.amount,
-        });
+    /// Sets a flat fee in lamports charged by `withdraw` on top of the token fee, paid by the
+    /// submitter into the `[b"sol_fees"]` PDA. `0` disables it.
+    pub fn set_sol_fee(ctx: Context<UpdateConfig>, sol_fee: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
+        ctx.accounts.config.sol_fee = sol_fee;
         Ok(())
     }
 
-    pub fn withdraw(ctx: Context<Withdraw>, finalize: State) -> Result<()> {
-        let req_acct = &ctx.accounts.request_account;
-        let clock = Clock::get()?;
+    /// Requires `withdraw` to present exactly the requested state instead of accepting a newer
+    /// signed one.
+    pub fn set_strict_finalize_height(
+        ctx: Context<UpdateConfig>,
+        strict_finalize_height: bool,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-        // 1. Checks
-        require!(req_acct.expiration > 0, ClearnetError::NoPendingRequest);
+        ctx.accounts.config.strict_finalize_height = strict_finalize_height;
+        Ok(())
+    }
+
+    /// Replaces the challenge period tiers for large withdrawals. Thresholds must be strictly
+    /// ascending and non-zero; an empty list leaves every request on the base period. A tier
+    /// shorter than a token's base period is applied as that base period.
+    pub fn set_challenge_tiers(
+        ctx: Context<UpdateConfig>,
+        tiers: Vec<ChallengeTier>,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
         require!(
-            clock.unix_timestamp >= req_acct.expiration,
-            ClearnetError::ChallengePeriodNotExpired
+            tiers.len() <= MAX_CHALLENGE_TIERS,
+            ClearnetError::InvalidChallengeTiers
         );
+        for (i, tier) in tiers.iter().enumerate() {
+            require!(
+                tier.threshold > 0 && (i == 0 || tier.threshold > tiers[i - 1].threshold),
+                ClearnetError::InvalidChallengeTiers
+            );
+            validate_challenge_period(tier.period)?;
+        }
+
+        let config = &mut ctx.accounts.config;
+        config.challenge_tiers = [ChallengeTier::default(); MAX_CHALLENGE_TIERS];
+        config.challenge_tiers[..tiers.len()].copy_from_slice(&tiers);
+        Ok(())
+    }
+
+    /// Requires at least `min_signers` distinct node signatures on top of the weighted quorum,
+    /// wherever the quorum is checked, so no single heavy node can carry it. `0` disables the
+    /// floor; the quorum still needs at least one signing node. It can't exceed the active node
+    /// set or `max_participants`, or nothing could ever be signed; deactivating nodes later
+    /// isn't held back by it, so lower it first when shrinking the set.
+    pub fn set_min_signers(ctx: Context<UpdateConfig>, min_signers: u8) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        let config = &ctx.accounts.config;
         require!(
-            finalize.height == req_acct.height,
-            ClearnetError::StateMismatch
+            min_signers as u32 <= config.active_node_count
+                && min_signers <= config.participant_cap(),
+            ClearnetError::InvalidMinSigners
         );
 
-        // 2. Transfer
-        let amount = req_acct.amount;
+        ctx.accounts.config.min_signers = min_signers;
+        Ok(())
+    }
 
-        // Seeds for signing
-        let bump = ctx.bumps.vault_token;
-        let seeds = &[
-            b"vault".as_ref(),
-            ctx.accounts.mint.to_account_info().key.as_ref(),
-            &[bump],
-        ];
-        let signer = &[&seeds[..]];
+    /// Sets the bond (in lamports) a node must still hold for its signature to count toward
+    /// quorum, so a partially slashed node doesn't; `0` counts every active node.
+    pub fn set_node_bond(ctx: Context<UpdateConfig>, node_bond: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-        let cpi_accounts = Transfer {
-            from: ctx.accounts.vault_token.to_account_info(),
-            to: ctx.accounts.user_token.to_account_info(),
-            authority: ctx.accounts.vault_token.to_account_info(), // The PDA is the owner
-        };
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            cpi_accounts,
-            signer,
-        );
-        token::transfer(cpi_ctx, amount)?;
+        ctx.accounts.config.node_bond = node_bond;
+        Ok(())
+    }
 
-        emit!(Withdrawn {
-            wallet: req_acct.wallet,
-            token: req_acct.token,
-            amount,
-        });
+    /// Deposits below `min_event_amount` skip the `Deposited` event to spare logs from
+    /// micro-deposits; they are still credited. `0` emits it for every deposit.
+    pub fn set_min_event_amount(ctx: Context<UpdateConfig>, min_event_amount: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
+        ctx.accounts.config.min_event_amount = min_event_amount;
         Ok(())
     }
-}
 
-// --- Accounts ---
+    /// Sets whether fees round up (in the fee recipient's favour) instead of down (in the user's).
+    pub fn set_fee_round_up(ctx: Context<UpdateConfig>, fee_round_up: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-#[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init, 
-        payer = admin, 
-        space = 8 + 32 + 8,
-        seeds = [b"config"], 
-        bump
-    )]
-    pub config: Account<'info, VaultConfig>,
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        ctx.accounts.config.fee_round_up = fee_round_up;
+        Ok(())
+    }
 
-#[derive(Accounts)]
-#[instruction(status: bool)]
-pub struct SetNodeStatus<'info> {
-    #[account(mut)]
-    pub admin: Signer<'info>,
-    #[account(
-        init_if_needed,
-        payer = admin,
-        space = 8 + 32 + 1,
-        seeds = [b"node", node_authority.key().as_ref()],
-        bump
-    )]
-    pub node_entry: Account<'info, NodeEntry>,
-    /// CHECK: The node's public key
-    pub node_authority: UncheckedAccount<'info>,
-    #[account(seeds = [b"config"], bump, has_one = admin)]
-    pub config: Account<'info, VaultConfig>,
-    pub system_program: Program<'info, System>,
-}
+    /// Toggles the redundant events that high-throughput deployments can do without.
+    pub fn set_verbose_events(ctx: Context<UpdateConfig>, verbose_events: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
 
-#[derive(Accounts)]
-pub struct Deposit<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
-    #[account(mut)]
-    pub user_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"vault", mint.key().as_ref()],
-        bump,
-        token::mint = mint,
-        token::authority = vault_token,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
+        ctx.accounts.config.verbose_events = verbose_events;
+        Ok(())
+    }
+
+    /// Toggles the single `RequestLifecycle` event emitted at every step of a request.
+    pub fn set_lifecycle_events(ctx: Context<UpdateConfig>, lifecycle_events: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.lifecycle_events = lifecycle_events;
+        Ok(())
+    }
+
+    /// Toggles `DepositAccounted`, emitted alongside `Deposited` for every deposit with the
+    /// resulting vault totals, so an exchange can drive its ledger from logs alone.
+    pub fn set_deposit_accounting_events(
+        ctx: Context<UpdateConfig>,
+        deposit_accounting_events: bool,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.deposit_accounting_events = deposit_accounting_events;
+        Ok(())
+    }
+
+    /// Toggles the per-step request events (`Requested`, `Challenged`, `Rejected`, `Withdrawn`,
+    /// `RequestSwept`), for deployments that index `RequestLifecycle` only.
+    pub fn set_granular_events(ctx: Context<UpdateConfig>, granular_events: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.granular_events = granular_events;
+        Ok(())
+    }
+
+    /// Pauses or resumes deposits, requests, challenges and withdrawals for every token.
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.paused = paused;
+        Ok(())
+    }
+
+    /// Pauses or resumes a single token's vault.
+    pub fn set_token_paused(ctx: Context<UpdateVaultState>, paused: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.vault_state.paused = paused;
+        Ok(())
+    }
+
+    /// Blocks `wallet` from the vault; the entry existing is what marks it blacklisted.
+    pub fn blacklist_wallet(ctx: Context<BlacklistWallet>, wallet: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.blacklist_entry.wallet = wallet;
+        Ok(())
+    }
+
+    /// Lifts a blacklisting; the entry is closed to the admin.
+    pub fn unblacklist_wallet(ctx: Context<UnblacklistWallet>) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Exempts `wallet` from the withdrawal fee; the entry existing is what marks it exempt.
+    pub fn add_fee_exemption(ctx: Context<AddFeeExemption>, wallet: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.fee_exemption.wallet = wallet;
+        Ok(())
+    }
+
+    /// Ends a fee exemption; the entry is closed to the admin.
+    pub fn remove_fee_exemption(ctx: Context<RemoveFeeExemption>) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )
+    }
+
+    /// Lists `mint` as an instant token: its requests can be withdrawn without waiting out the
+    /// challenge period. This trusts the operator to back the token whatever a challenge would
+    /// have shown, so only list tokens it fully stands behind. The entry existing is what
+    /// marks the mint.
+    pub fn add_instant_token(ctx: Context<AddInstantToken>, mint: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.instant_token.mint = mint;
+        emit!(InstantTokenListed {
+            mint,
+            listed: true,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Unlists an instant token, so its requests wait out the challenge period again; the
+    /// entry is closed to the admin.
+    pub fn remove_instant_token(ctx: Context<RemoveInstantToken>) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        emit!(InstantTokenListed {
+            mint: ctx.accounts.instant_token.mint,
+            listed: false,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Caps the number of simultaneously open withdrawal requests; `0` means no cap.
+    pub fn set_max_open_requests(ctx: Context<UpdateConfig>, max_open_requests: u32) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.max_open_requests = max_open_requests;
+        Ok(())
+    }
+
+    /// Sets the lamports each `request` escrows as a reward for a successful challenger.
+    pub fn set_challenge_bounty(ctx: Context<UpdateConfig>, challenge_bounty: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.challenge_bounty = challenge_bounty;
+        Ok(())
+    }
+
+    /// Seconds a node may go without a `heartbeat` before it stops counting towards quorum, on
+    /// every instruction that checks one; `0` disables liveness checks.
+    pub fn set_heartbeat_window(ctx: Context<UpdateConfig>, heartbeat_window: i64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(heartbeat_window >= 0, ClearnetError::InvalidHeartbeatWindow);
+
+        ctx.accounts.config.heartbeat_window = heartbeat_window;
+        Ok(())
+    }
+
+    /// Whether anyone may call `sweep_expired_request`, or only active nodes.
+    pub fn set_sweep_permissionless(
+        ctx: Context<UpdateConfig>,
+        sweep_permissionless: bool,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.sweep_permissionless = sweep_permissionless;
+        Ok(())
+    }
+
+    /// Seconds an unclaimed request stays withdrawable after its challenge period before
+    /// `sweep_expired_request` may close it.
+    pub fn set_sweep_delay(ctx: Context<UpdateConfig>, sweep_delay: i64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(sweep_delay >= 0, ClearnetError::InvalidSweepDelay);
+
+        ctx.accounts.config.sweep_delay = sweep_delay;
+        Ok(())
+    }
+
+    /// Whether nodes sign `withdrawal_amount_message(state, recipient, amount)` for each new
+    /// request instead of the bare state, binding their signatures to the exact amount rather
+    /// than letting the requester pick any amount up to `balance`.
+    pub fn set_sign_amounts(ctx: Context<UpdateConfig>, sign_amounts: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.sign_amounts = sign_amounts;
+        Ok(())
+    }
+
+    /// Lamports paid to whoever sweeps an expired request, out of the request's rent and
+    /// bounty; the wallet gets the rest.
+    pub fn set_sweep_reward(ctx: Context<UpdateConfig>, sweep_reward: u64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.sweep_reward = sweep_reward;
+        Ok(())
+    }
+
+    /// Seconds a deactivated node must wait before `set_node_status` can reactivate it.
+    pub fn set_reactivation_cooldown(
+        ctx: Context<UpdateConfig>,
+        reactivation_cooldown: i64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(
+            reactivation_cooldown >= 0,
+            ClearnetError::InvalidReactivationCooldown
+        );
+
+        ctx.accounts.config.reactivation_cooldown = reactivation_cooldown;
+        Ok(())
+    }
+
+    /// Lets deposits and withdrawals use token accounts other than the user's associated token
+    /// account, for integrators that can't use ATAs. Off by default.
+    pub fn set_allow_non_ata(ctx: Context<UpdateConfig>, allow_non_ata: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.allow_non_ata = allow_non_ata;
+        Ok(())
+    }
+
+    /// Whether an admin key may also be registered as a node. Off by default, so the admins
+    /// can't challenge (or co-sign) the requests they oversee.
+    pub fn set_admin_can_be_node(
+        ctx: Context<UpdateConfig>,
+        admin_can_be_node: bool,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        ctx.accounts.config.admin_can_be_node = admin_can_be_node;
+        Ok(())
+    }
+
+    pub fn update_dispute_window(ctx: Context<UpdateConfig>, dispute_window: i64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(dispute_window >= 0, ClearnetError::InvalidDisputeWindow);
+
+        ctx.accounts.config.dispute_window = dispute_window;
+        Ok(())
+    }
+
+    pub fn get_config(ctx: Context<GetConfig>) -> Result<ConfigView> {
+        let config = &ctx.accounts.config;
+        Ok(ConfigView {
+            version: CONFIG_VIEW_VERSION,
+            admin: config.admin,
+            challenge_period: config.challenge_period,
+            admins: config.admins,
+            threshold: config.threshold,
+            fee_bps: config.fee_bps,
+            fee_recipient: config.fee_recipient,
+            dispute_window: config.dispute_window,
+            instant_fee_bps: config.instant_fee_bps,
+            verbose_events: config.verbose_events,
+            paused: config.paused,
+            open_request_count: config.open_request_count,
+            max_open_requests: config.max_open_requests,
+            challenge_bounty: config.challenge_bounty,
+            heartbeat_window: config.heartbeat_window,
+            sweep_permissionless: config.sweep_permissionless,
+            sweep_delay: config.sweep_delay,
+            admin_can_be_node: config.admin_can_be_node,
+            created_at: config.created_at,
+            created_slot: config.created_slot,
+            allow_non_ata: config.allow_non_ata,
+            lifecycle_events: config.lifecycle_events,
+            granular_events: config.granular_events,
+            reactivation_cooldown: config.reactivation_cooldown,
+            fee_round_up: config.fee_round_up,
+            max_participants: config.max_participants,
+            config_version: config.version,
+            sol_fee: config.sol_fee,
+            min_event_amount: config.min_event_amount,
+            strict_finalize_height: config.strict_finalize_height,
+            challenge_tiers: config.challenge_tiers,
+            min_signers: config.min_signers,
+            timelock_delay: config.timelock_delay,
+            pending_fee_recipient: config.pending_fee_recipient,
+            fee_recipient_eta: config.fee_recipient_eta,
+            slash_split_bps: config.slash_split_bps,
+            node_bond: config.node_bond,
+            challenge_lockout: config.challenge_lockout,
+            min_deposit: config.min_deposit,
+            sweep_reward: config.sweep_reward,
+            sign_amounts: config.sign_amounts,
+            max_single_weight_bps: config.max_single_weight_bps,
+            max_state_lifetime: config.max_state_lifetime,
+            activation_grace: config.activation_grace,
+            deposit_accounting_events: config.deposit_accounting_events,
+        })
+    }
+
+    /// Previews the payout of a withdrawal of `amount` at the current fee, without moving funds.
+    pub fn quote_withdraw(ctx: Context<GetConfig>, amount: u64) -> Result<WithdrawQuote> {
+        let fee = compute_fee(
+            amount,
+            ctx.accounts.config.fee_bps,
+            ctx.accounts.config.fee_round_up,
+        );
+        Ok(WithdrawQuote {
+            net: amount - fee,
+            fee,
+        })
+    }
+
+    /// Returns a registered node's status, so clients selecting signers don't have to decode
+    /// `NodeEntry` themselves.
+    pub fn get_node(ctx: Context<GetNode>, _node_authority: Pubkey) -> Result<NodeView> {
+        let node_entry = &ctx.accounts.node_entry;
+        Ok(NodeView {
+            version: NODE_VIEW_VERSION,
+            authority: node_entry.authority,
+            is_active: node_entry.is_active,
+            weight: node_entry.weight,
+            last_seen: node_entry.last_seen,
+            bond: node_entry.bond,
+            deactivated_at: node_entry.deactivated_at,
+            entry_version: node_entry.version,
+            activated_at: node_entry.activated_at,
+        })
+    }
+
+    /// Succeeds only if `mint`'s vault holds at least its liabilities (see
+    /// `VaultState::liabilities`), so keepers can simulate it and alert when it fails.
+    pub fn assert_solvent(ctx: Context<AssertSolvent>, _mint: Pubkey) -> Result<()> {
+        require!(
+            ctx.accounts.vault_token.amount as u128 >= ctx.accounts.vault_state.liabilities(),
+            ClearnetError::VaultInsolvent
+        );
+        Ok(())
+    }
+
+    /// Queues `new_total` as `mint`'s `total_deposited`, applied by `reconcile_total` once
+    /// `timelock_delay` has passed. For recovering from a desync between the books and the
+    /// vault; replaces any reconciliation queued before.
+    pub fn queue_reconcile_total(
+        ctx: Context<QueueReconcileTotal>,
+        mint: Pubkey,
+        new_total: u64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        let eta = Clock::get()?.unix_timestamp + ctx.accounts.config.timelock_delay;
+        let pending = &mut ctx.accounts.pending;
+        pending.new_total = new_total;
+        pending.eta = eta;
+        emit!(TotalReconcileQueued {
+            mint,
+            new_total,
+            eta,
+        });
+        Ok(())
+    }
+
+    /// Sets `mint`'s `total_deposited` to the value queued by `queue_reconcile_total` once its
+    /// delay is over. Never above what the vault holds, so the books can't claim more assets
+    /// than exist.
+    pub fn reconcile_total(ctx: Context<ReconcileTotal>, mint: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        let pending = &ctx.accounts.pending;
+        require!(
+            Clock::get()?.unix_timestamp >= pending.eta,
+            ClearnetError::TimelockNotExpired
+        );
+        require!(
+            pending.new_total <= ctx.accounts.vault_token.amount,
+            ClearnetError::ReconcileExceedsBalance
+        );
+
+        let vault_state = &mut ctx.accounts.vault_state;
+        let old = vault_state.total_deposited;
+        vault_state.total_deposited = pending.new_total;
+        emit!(TotalReconciled {
+            mint,
+            old,
+            new: pending.new_total,
+        });
+        Ok(())
+    }
+
+    /// Returns every limit that applies to deposits and withdrawals of `vault_state`'s mint,
+    /// so frontends can render them in one read.
+    pub fn get_limits(ctx: Context<GetLimits>) -> Result<Limits> {
+        let config = &ctx.accounts.config;
+        let vault_state = &ctx.accounts.vault_state;
+        let open_request_headroom = match config.max_open_requests {
+            0 => u32::MAX,
+            max => max.saturating_sub(config.open_request_count),
+        };
+        Ok(Limits {
+            version: LIMITS_VERSION,
+            min_deposit: config.min_deposit_for(ctx.accounts.mint.decimals),
+            max_deposit: vault_state.max_deposit,
+            deposit_cap_headroom: vault_state.deposit_cap_headroom(),
+            withdraw_rate_limit: vault_state.daily_withdraw_limit,
+            daily_cap_remaining: vault_state.daily_withdraw_remaining(Clock::get()?.unix_timestamp),
+            challenge_period: vault_state.challenge_period(config),
+            min_withdraw: vault_state.min_withdraw,
+            open_request_headroom,
+            max_single_withdraw: vault_state.max_single_withdraw,
+        })
+    }
+
+    /// Returns how many nodes are active and how many signatures (and how much weight) a state
+    /// needs to reach quorum, so clients know what to collect.
+    pub fn get_quorum_requirement(ctx: Context<GetConfig>) -> Result<QuorumRequirement> {
+        let config = &ctx.accounts.config;
+        Ok(QuorumRequirement {
+            active_node_count: config.active_node_count,
+            quorum: quorum_of(config.active_node_count as u64) as u32,
+            weight_quorum: quorum_of(config.active_node_weight),
+            min_signers: config.min_signers,
+        })
+    }
+
+    /// Returns a snapshot of `mint`'s vault for monitoring, so one simulated call can alert on
+    /// a pause, a shrinking node set or the vault balance drifting from its deposits.
+    pub fn health(ctx: Context<GetHealth>, _mint: Pubkey) -> Result<Health> {
+        let config = &ctx.accounts.config;
+        let vault_state = &ctx.accounts.vault_state;
+        Ok(Health {
+            version: HEALTH_VERSION,
+            paused: config.paused || vault_state.paused,
+            active_node_count: config.active_node_count,
+            quorum: quorum_of(config.active_node_count as u64) as u32,
+            open_request_count: config.open_request_count,
+            total_deposited: vault_state.total_deposited,
+            vault_balance: ctx.accounts.vault_token.amount,
+        })
+    }
+
+    /// Returns a pending request's fields as versioned return data, so clients can read it
+    /// without depending on the raw `WithdrawalRequest` layout.
+    pub fn get_request(ctx: Context<GetRequest>) -> Result<RequestView> {
+        let request = &ctx.accounts.request_account;
+        Ok(RequestView {
+            version: REQUEST_VIEW_VERSION,
+            wallet: request.wallet,
+            token: request.token,
+            amount: request.amount,
+            height: request.height,
+            expiration: request.expiration,
+            balance: request.balance,
+            bounty: request.bounty,
+            da_hash: request.da_hash,
+        })
+    }
+
+    pub fn set_node_status(ctx: Context<SetNodeStatus>, status: bool) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        // A redundant toggle would only burn a transaction and blur the activation history.
+        require!(
+            ctx.accounts.node_entry.is_active != status,
+            ClearnetError::NoStatusChange
+        );
+        if status {
+            ctx.accounts
+                .config
+                .require_node_allowed(&ctx.accounts.node_authority.key())?;
+
+            // Activate: The node account is initialized by Anchor (init_if_needed)
+            // We just ensure it's marked active if we add a flag, but purely existing is enough?
+            // Let's use a flag in case we want to ban them without closing the account (to keep history? no).
+            // Actually, closing the account is better for rent.
+            // But the instruction `SetNodeStatus` with `status=false` should close it.
+            // Anchor's `close` constraint handles closing.
+            // Here we just initialize. logic is handled by Account constraints in the struct.
+        } else {
+            // Deactivate is handled by `close` in a separate instruction usually,
+            // or we just assume if it exists it's active.
+            // But to toggle `status` we might need a bool in the account.
+        }
+
+        if !status && ctx.accounts.node_entry.bond > 0 {
+            let escrow = ctx
+                .accounts
+                .exiting_bond
+                .as_mut()
+                .ok_or(ClearnetError::MissingBondEscrow)?;
+            escrow.authority = ctx.accounts.node_authority.key();
+            escrow_bond(
+                &mut ctx.accounts.node_entry,
+                escrow,
+                ctx.accounts.config.dispute_window,
+            )?;
+        }
+
+        let node_entry = &mut ctx.accounts.node_entry;
+        if node_entry.authority == Pubkey::default() {
+            node_entry.weight = 1;
+            node_entry.version = NODE_ENTRY_VERSION;
+        }
+        let now = Clock::get()?.unix_timestamp;
+        if status {
+            // Stops a node flapping in and out of the active set.
+            require!(
+                now >= node_entry.deactivated_at + ctx.accounts.config.reactivation_cooldown,
+                ClearnetError::ReactivationCooldown
+            );
+            // Activation counts as a sign of life, so a fresh node isn't immediately stale.
+            node_entry.last_seen = now;
+            if !node_entry.is_active {
+                ctx.accounts.config.require_weight_within_limits(
+                    ctx.accounts.config.active_node_weight,
+                    node_entry.weight,
+                )?;
+                node_entry.activated_at = now;
+            }
+        }
+        if !status {
+            node_entry.deactivated_at = now;
+        }
+        ctx.accounts
+            .config
+            .track_node(node_entry.weight, node_entry.is_active, status);
+        node_entry.is_active = status;
+        if !status {
+            emit_node_set_changed(&ctx.accounts.config, node_entry.authority, now);
+        }
+        // The entry is seeded by the authority, so this only takes effect on creation.
+        // Changing a node's key goes through `rotate_node_key`.
+        node_entry.authority = ctx.accounts.node_authority.key();
+        Ok(())
+    }
+
+    pub fn rotate_node_key(ctx: Context<RotateNodeKey>, new_authority: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        ctx.accounts.config.require_node_allowed(&new_authority)?;
+        // The escrow is keyed by the old authority, so rotating would strand it and let the
+        // node sign on under a key its dispute window doesn't cover. Claim or slash it first.
+        let exiting = &ctx.accounts.exiting_bond;
+        let escrowed = exiting.owner == &crate::ID
+            && ExitingBond::try_deserialize(&mut &exiting.data.borrow()[..])
+                .is_ok_and(|escrow| escrow.amount > 0);
+        require!(!escrowed, ClearnetError::BondEscrowPending);
+
+        // Migrate the entry to the PDA of the new key; the old one is closed by Anchor.
+        let old_entry = &ctx.accounts.node_entry;
+        let new_entry = &mut ctx.accounts.new_node_entry;
+        new_entry.authority = new_authority;
+        new_entry.is_active = old_entry.is_active;
+        new_entry.bond = old_entry.bond;
+        new_entry.weight = old_entry.weight;
+        new_entry.last_seen = old_entry.last_seen;
+        new_entry.deactivated_at = old_entry.deactivated_at;
+        new_entry.version = old_entry.version;
+        new_entry.activated_at = old_entry.activated_at;
+        // Carry the bond lamports over before Anchor closes the old entry to the admin.
+        old_entry.sub_lamports(old_entry.bond)?;
+        new_entry.add_lamports(old_entry.bond)?;
+
+        emit!(NodeKeyRotated {
+            old_authority: old_entry.authority,
+            new_authority,
+        });
+
+        Ok(())
+    }
+
+    /// Caps the share of the active weight, in basis points, any one node may hold after
+    /// `set_node_weight` or activation; `0` disables the cap. Deactivations aren't checked, so
+    /// the remaining nodes' shares can exceed it until weights are adjusted.
+    pub fn set_max_single_weight_bps(
+        ctx: Context<UpdateConfig>,
+        max_single_weight_bps: u16,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(
+            max_single_weight_bps <= 10_000,
+            ClearnetError::InvalidWeightCap
+        );
+
+        ctx.accounts.config.max_single_weight_bps = max_single_weight_bps;
+        Ok(())
+    }
+
+    /// Seconds a node must have been active before its signatures count toward quorum, on every
+    /// instruction that checks one; until then they are ignored, not rejected. `0` disables the
+    /// grace.
+    pub fn set_activation_grace(ctx: Context<UpdateConfig>, activation_grace: i64) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(activation_grace >= 0, ClearnetError::InvalidActivationGrace);
+
+        ctx.accounts.config.activation_grace = activation_grace;
+        Ok(())
+    }
+
+    /// Caps how many seconds past now a requested or challenging state's `deadline` may lie;
+    /// `0` disables the cap. With a cap set, states without a deadline are refused.
+    pub fn set_max_state_lifetime(
+        ctx: Context<UpdateConfig>,
+        max_state_lifetime: i64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(max_state_lifetime >= 0, ClearnetError::InvalidStateLifetime);
+
+        ctx.accounts.config.max_state_lifetime = max_state_lifetime;
+        Ok(())
+    }
+
+    /// Sets a node's voting weight towards `weight_quorum`. Weight is independent of the bond.
+    pub fn set_node_weight(
+        ctx: Context<SetNodeWeight>,
+        node_authority: Pubkey,
+        weight: u64,
+    ) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        require!(weight > 0, ClearnetError::InvalidNodeWeight);
+
+        let node_entry = &mut ctx.accounts.node_entry;
+        let old_weight = node_entry.weight;
+        // Inactive nodes are checked when they're activated.
+        if node_entry.is_active {
+            let config = &mut ctx.accounts.config;
+            let others = config.active_node_weight - old_weight;
+            config.require_weight_within_limits(others, weight)?;
+            config.active_node_weight = others + weight;
+        }
+        node_entry.weight = weight;
+
+        emit!(NodeWeightChanged {
+            node: node_authority,
+            old_weight,
+            new_weight: weight,
+        });
+
+        Ok(())
+    }
+
+    /// Records that the calling node is alive.
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        ctx.accounts.node_entry.last_seen = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    /// Reports which of the `NodeEntry` accounts passed in `remaining_accounts` are active but
+    /// haven't sent a heartbeat in the last `staleness` seconds, and how many remain live.
+    pub fn get_node_liveness(ctx: Context<GetConfig>, staleness: i64) -> Result<NodeLiveness> {
+        let now = Clock::get()?.unix_timestamp;
+        let mut liveness = NodeLiveness {
+            live_count: 0,
+            stale: Vec::new(),
+        };
+        for entry in ctx.remaining_accounts.iter().filter_map(load_node_entry) {
+            if !entry.is_active {
+                continue;
+            }
+            if entry.is_live(staleness, now) {
+                liveness.live_count += 1;
+            } else {
+                liveness.stale.push(entry.authority);
+            }
+        }
+        Ok(liveness)
+    }
+
+    /// Adds `amount` lamports to the caller's node bond, held by its `NodeEntry`.
+    pub fn bond_node(ctx: Context<BondNode>, amount: u64) -> Result<()> {
+        require!(amount > 0, ClearnetError::InvalidBondAmount);
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.node_entry.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_ctx, amount)?;
+
+        let node_entry = &mut ctx.accounts.node_entry;
+        node_entry.bond += amount;
+
+        emit!(NodeBonded {
+            node: node_entry.authority,
+            amount,
+            bond: node_entry.bond,
+        });
+
+        Ok(())
+    }
+
+    /// Lets a node leave on its own: it is deactivated and its bond moves to the exit escrow,
+    /// claimable with `claim_node_bond` once the dispute window has passed.
+    pub fn exit_node(ctx: Context<ExitNode>) -> Result<()> {
+        require!(
+            ctx.accounts.node_entry.is_active,
+            ClearnetError::NodeNotActive
+        );
+        retire_node(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.node_entry,
+            &mut ctx.accounts.exiting_bond,
+        )
+    }
+
+    /// Deactivates a node that hasn't sent a heartbeat within `heartbeat_window`. Stale nodes
+    /// are already ignored as signers, but while active they still count towards the quorum
+    /// thresholds; this takes them out of `active_node_count` and `active_node_weight` too.
+    /// Permissionless; the caller pays the exit escrow's rent, and the bond goes there as
+    /// with `exit_node`.
+    pub fn deactivate_stale_node(ctx: Context<DeactivateStaleNode>) -> Result<()> {
+        let window = ctx.accounts.config.heartbeat_window;
+        let node_entry = &ctx.accounts.node_entry;
+        require!(node_entry.is_active, ClearnetError::NodeNotActive);
+        require!(
+            window > 0 && !node_entry.is_live(window, Clock::get()?.unix_timestamp),
+            ClearnetError::NodeNotStale
+        );
+        retire_node(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.node_entry,
+            &mut ctx.accounts.exiting_bond,
+        )
+    }
+
+    /// Returns an exiting node's escrowed bond once the dispute window has passed.
+    pub fn claim_node_bond(ctx: Context<ClaimNodeBond>) -> Result<()> {
+        let escrow = &ctx.accounts.exiting_bond;
+        require!(
+            Clock::get()?.unix_timestamp >= escrow.unlock_ts,
+            ClearnetError::BondLocked
+        );
+
+        // The escrow is closed to the authority, releasing the bond together with its rent.
+        emit!(NodeBondClaimed {
+            node: escrow.authority,
+            amount: escrow.amount,
+        });
+
+        Ok(())
+    }
+
+    /// Slashes a node that signed two conflicting states for the same wallet, token and height:
+    /// a different balance, participant set or DA hash. A different `deadline` alone is a
+    /// re-signing of the same state, not a conflict. The node's signature in each state, over its `hash_state`, must be checked by an
+    /// Ed25519Program instruction in the same transaction (one per state, or both in one).
+    ///
+    /// Both the live bond and any escrowed bond still inside its dispute window go to the reporter.
+    pub fn report_equivocation(
+        ctx: Context<ReportEquivocation>,
+        state_a: State,
+        state_b: State,
+    ) -> Result<()> {
+        let node = ctx.accounts.node_entry.authority;
+        require!(
+            state_a.wallet == state_b.wallet
+                && state_a.token == state_b.token
+                && state_a.height == state_b.height
+                && (state_a.balance != state_b.balance
+                    || state_a.participants != state_b.participants
+                    || state_a.da_hash != state_b.da_hash),
+            ClearnetError::NotEquivocation
+        );
+        let (hash_a, hash_b) = (hash_state(&state_a)?, hash_state(&state_b)?);
+        // Reordered participants hash the same, so that alone isn't a second statement.
+        require!(hash_a != hash_b, ClearnetError::NotEquivocation);
+        for (state, hash) in [(&state_a, &hash_a), (&state_b, &hash_b)] {
+            prevalidate_signatures(&state.participants, &state.sigs)?;
+            let index = state
+                .participants
+                .iter()
+                .position(|participant| *participant == node)
+                .ok_or(ClearnetError::NotEquivocation)?;
+            verify_ed25519_signatures(
+                &ctx.accounts.instructions,
+                &[node],
+                &state.sigs[index..=index],
+                hash,
+            )?;
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let reporter = ctx.accounts.reporter.to_account_info();
+
+        let node_entry = &mut ctx.accounts.node_entry;
+        let mut slashed = node_entry.bond;
+        node_entry.sub_lamports(slashed)?;
+        ensure_rent_exempt(&node_entry.to_account_info(), &Rent::get()?)?;
+        node_entry.bond = 0;
+        ctx.accounts
+            .config
+            .track_node(node_entry.weight, node_entry.is_active, false);
+        if node_entry.is_active {
+            node_entry.deactivated_at = now;
+            emit_node_set_changed(&ctx.accounts.config, node_entry.authority, now);
+        }
+        node_entry.is_active = false;
+
+        if let Some(escrow) = ctx.accounts.exiting_bond.as_mut() {
+            if now < escrow.unlock_ts {
+                escrow.sub_lamports(escrow.amount)?;
+                ensure_rent_exempt(&escrow.to_account_info(), &Rent::get()?)?;
+                slashed += escrow.amount;
+                escrow.amount = 0;
+            }
+        }
+
+        require!(slashed > 0, ClearnetError::NothingToSlash);
+        // Part goes to the treasury, so a node can't simply profit from removing a competitor.
+        let treasury_amount =
+            (slashed as u128 * ctx.accounts.config.slash_split_bps as u128 / 10_000) as u64;
+        let reporter_amount = slashed - treasury_amount;
+        reporter.add_lamports(reporter_amount)?;
+        let treasury = &mut ctx.accounts.treasury;
+        treasury.add_lamports(treasury_amount)?;
+        treasury.slashed += treasury_amount;
+
+        emit!(NodeSlashed {
+            node,
+            reporter: reporter.key(),
+            amount: slashed,
+            height_a: state_a.height,
+            height_b: state_b.height,
+            ts: now,
+        });
+        emit!(SlashDistributed {
+            node,
+            reporter: reporter.key(),
+            reporter_amount,
+            treasury_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the vault token account and `VaultState` for `mint`. Permissionless; the caller
+    /// pays the rent, once per mint. Listing the mint in the `TokenRegistry` is up to the
+    /// admins (`register_token`), so nobody can fill the registry with vaults of junk mints.
+    pub fn create_vault(ctx: Context<CreateVault>) -> Result<()> {
+        // Persist the canonical vault bump; later instructions reuse it.
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.mint = ctx.accounts.mint.key();
+        vault_state.bump = ctx.bumps.vault_token;
+
+        emit!(VaultCreated {
+            mint: vault_state.mint,
+            vault: ctx.accounts.vault_token.key(),
+        });
+
+        Ok(())
+    }
+
+    /// Lists the mint of an existing vault in the `TokenRegistry`, creating the registry on
+    /// first use.
+    pub fn register_token(ctx: Context<RegisterToken>) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        let mint = ctx.accounts.vault_state.mint;
+        let registry = &mut ctx.accounts.token_registry;
+        require!(
+            !registry.mints.contains(&mint),
+            ClearnetError::TokenAlreadyRegistered
+        );
+        require!(
+            registry.mints.len() < MAX_REGISTERED_TOKENS,
+            ClearnetError::TooManyTokens
+        );
+        registry.mints.push(mint);
+
+        emit!(TokenRegistryUpdated {
+            mint,
+            listed: true,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Drops `mint` from the `TokenRegistry`, freeing its slot. The vault itself is untouched.
+    pub fn unregister_token(ctx: Context<UnregisterToken>, mint: Pubkey) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+
+        let registry = &mut ctx.accounts.token_registry;
+        let index = registry
+            .mints
+            .iter()
+            .position(|m| *m == mint)
+            .ok_or(ClearnetError::TokenNotRegistered)?;
+        registry.mints.remove(index);
+
+        emit!(TokenRegistryUpdated {
+            mint,
+            listed: false,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Returns the vault token account and its bump, so clients needn't derive the PDA.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<VaultAddress> {
+        // Transfer Tokens/SOL to Vault
+        // For simplicity, we implement SPL Token transfer.
+        // If native SOL, one would wrap it or use SystemProgram transfer to a PDA.
+        // We assume USDC (SPL) for this implementation as per README "USDT".
+
+        let mut vault_state = load_vault_state(&ctx.accounts.vault_state)?;
+        assert_operational(&ctx.accounts.config, &vault_state, &ctx.accounts.blacklist)?;
+        require_vault_token(&ctx.accounts.vault_token, &vault_state)?;
+        require_deposit_mints(
+            &ctx.accounts.user_token,
+            &ctx.accounts.vault_token,
+            ctx.accounts.mint.key(),
+        )?;
+        require_canonical_token_account(
+            &ctx.accounts.config,
+            &ctx.accounts.user_token.key(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.mint.key(),
+        )?;
+        require_min_deposit(&ctx.accounts.config, &ctx.accounts.mint, amount)?;
+        vault_state.require_deposit_within_limits(amount)?;
+
+        vault_state.total_deposited += amount;
+        save_vault_state(&ctx.accounts.vault_state, &vault_state)?;
+        transfer_to_vault(
+            &ctx.accounts.config,
+            &ctx.accounts.token_program,
+            &ctx.accounts.user,
+            &ctx.accounts.user_token,
+            &ctx.accounts.vault_token,
+            ctx.accounts.mint.key(),
+            amount,
+        )?;
+        emit_deposit_accounted(
+            &ctx.accounts.config,
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            amount,
+            vault_state.total_deposited,
+            &ctx.accounts.vault_token,
+        )?;
+
+        Ok(VaultAddress {
+            vault: ctx.accounts.vault_token.key(),
+            bump: vault_state.bump,
+        })
+    }
+
+    /// Deposits from `user_token` without its owner signing. The owner must first have
+    /// approved the program's delegate PDA (`[b"delegate"]`) for at least `amount` with SPL
+    /// `approve`; the deposit is credited to the owner, not the caller.
+    pub fn deposit_from_delegate(ctx: Context<DepositFromDelegate>, amount: u64) -> Result<()> {
+        let mut vault_state = load_vault_state(&ctx.accounts.vault_state)?;
+        assert_operational(&ctx.accounts.config, &vault_state, &ctx.accounts.blacklist)?;
+        require_vault_token(&ctx.accounts.vault_token, &vault_state)?;
+        require_deposit_mints(
+            &ctx.accounts.user_token,
+            &ctx.accounts.vault_token,
+            ctx.accounts.mint.key(),
+        )?;
+        let user_token = &ctx.accounts.user_token;
+        require_canonical_token_account(
+            &ctx.accounts.config,
+            &user_token.key(),
+            &user_token.owner,
+            &ctx.accounts.mint.key(),
+        )?;
+        require!(
+            user_token.delegate == COption::Some(ctx.accounts.delegate.key()),
+            ClearnetError::DelegateNotApproved
+        );
+        require!(
+            user_token.delegated_amount >= amount,
+            ClearnetError::InsufficientDelegation
+        );
+        require_min_deposit(&ctx.accounts.config, &ctx.accounts.mint, amount)?;
+        vault_state.require_deposit_within_limits(amount)?;
+
+        vault_state.total_deposited += amount;
+        save_vault_state(&ctx.accounts.vault_state, &vault_state)?;
+        let seeds = &[b"delegate".as_ref(), &[ctx.bumps.delegate]];
+        let signer = &[&seeds[..]];
+        let cpi_accounts = Transfer {
+            from: user_token.to_account_info(),
+            to: ctx.accounts.vault_token.to_account_info(),
+            authority: ctx.accounts.delegate.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            cpi_accounts,
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        emit_deposited(
+            &ctx.accounts.config,
+            user_token.owner,
+            ctx.accounts.mint.key(),
+            amount,
+        );
+        emit_deposit_accounted(
+            &ctx.accounts.config,
+            user_token.owner,
+            ctx.accounts.mint.key(),
+            amount,
+            vault_state.total_deposited,
+            &ctx.accounts.vault_token,
+        )?;
+        Ok(())
+    }
+
+    /// Records that the node quorum acknowledged the off-chain deposit `deposit_ref` at ledger
+    /// `height`, for bridges that need an on-chain attestation. Every active node passed in
+    /// `remaining_accounts` whose Ed25519 signature over `deposit_confirmation_message` is in
+    /// the transaction counts; together they must reach quorum by count and by weight, and be
+    /// at least `min_signers` distinct nodes.
+    pub fn confirm_deposit(
+        ctx: Context<ConfirmDeposit>,
+        deposit_ref: [u8; 32],
+        height: u64,
+    ) -> Result<()> {
+        let confirmation = &mut ctx.accounts.confirmation;
+        require!(
+            confirmation.confirmed_at == 0,
+            ClearnetError::DepositAlreadyConfirmed
+        );
+
+        let config = &ctx.accounts.config;
+        let message = deposit_confirmation_message(&deposit_ref, height);
+        let (_, weight) = require_quorum(
+            &ctx.accounts.instructions,
+            ctx.remaining_accounts,
+            &message,
+            config,
+        )?;
+
+        let ts = Clock::get()?.unix_timestamp;
+        confirmation.deposit_ref = deposit_ref;
+        confirmation.height = height;
+        confirmation.confirmed_at = ts;
+        emit!(DepositConfirmed {
+            deposit_ref,
+            height,
+            signer_weight: weight,
+            ts,
+        });
+        Ok(())
+    }
+
+    /// Returns when the request becomes withdrawable, so frontends needn't fetch the account.
+    ///
+    /// `remaining_accounts` are the `NodeEntry` accounts of `state.participants`, in order; the
+    /// nodes among them must reach the quorum (see `verify_state_signatures`).
+    pub fn request(ctx: Context<Request>, state: State, amount: u64) -> Result<RequestReceipt> {
+        state.validate()?;
+        assert_operational(
+            &ctx.accounts.config,
+            &ctx.accounts.vault_state,
+            &ctx.accounts.blacklist,
+        )?;
+        ctx.accounts
+            .config
+            .require_deadline_within_lifetime(&state, Clock::get()?.unix_timestamp)?;
+
+        // 1. Validation
+        // The request PDA is the user's, so the state has to be theirs too.
+        require_keys_eq!(
+            state.wallet,
+            ctx.accounts.user.key(),
+            ClearnetError::WalletMismatch
+        );
+        require!(state.balance > 0, ClearnetError::EmptyState);
+        require!(
+            amount <= state.balance,
+            ClearnetError::InsufficientStateBalance
+        );
+        require!(
+            amount >= ctx.accounts.vault_state.min_withdraw,
+            ClearnetError::WithdrawTooSmall
+        );
+        ctx.accounts
+            .vault_state
+            .require_within_single_withdraw_cap(amount)?;
+        require!(
+            ctx.accounts.request_account.expiration == 0,
+            ClearnetError::RequestAlreadyPending
+        ); // Assuming 0 means not active
+
+        // 2. Verify Signatures
+        // Every participant's signature over `hash_state(state)` (or, with `sign_amounts`, over
+        // the amount being requested) must be in the Ed25519Program instruction right before
+        // this one, and the signing nodes must reach the quorum.
+        ctx.accounts
+            .config
+            .require_participants_within_cap(&state.participants)?;
+        let (node_accounts, _) =
+            split_participant_accounts(&state.participants, ctx.remaining_accounts)?;
+        verify_request_signatures(
+            &ctx.accounts.config,
+            &ctx.accounts.instructions,
+            node_accounts,
+            &state,
+            &ctx.accounts.user.key(),
+            amount,
+        )?;
+
+        // 3. Store Request
+        ctx.accounts.request_account.bump = ctx.bumps.request_account;
+        store_request(
+            &ctx.accounts.user,
+            &mut ctx.accounts.request_account,
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.vault_state,
+            &ctx.accounts.system_program,
+            &state,
+            amount,
+        )
+    }
+
+    /// `deposit` followed by `request` in one instruction, for clients (e.g. rebalancing bots)
+    /// that queue a withdrawal right after funding the vault. Each half is validated as it
+    /// would be on its own, and `state` must be for the deposited mint. `remaining_accounts`
+    /// are as for `request`.
+    pub fn deposit_and_request(
+        ctx: Context<DepositAndRequest>,
+        deposit_amount: u64,
+        state: State,
+        request_amount: u64,
+    ) -> Result<()> {
+        let mut vault_state = load_vault_state(&ctx.accounts.vault_state)?;
+        assert_operational(&ctx.accounts.config, &vault_state, &ctx.accounts.blacklist)?;
+        require_vault_token(&ctx.accounts.vault_token, &vault_state)?;
+        require_deposit_mints(
+            &ctx.accounts.user_token,
+            &ctx.accounts.vault_token,
+            ctx.accounts.mint.key(),
+        )?;
+        require_canonical_token_account(
+            &ctx.accounts.config,
+            &ctx.accounts.user_token.key(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.mint.key(),
+        )?;
+        require_min_deposit(&ctx.accounts.config, &ctx.accounts.mint, deposit_amount)?;
+        vault_state.require_deposit_within_limits(deposit_amount)?;
+
+        transfer_to_vault(
+            &ctx.accounts.config,
+            &ctx.accounts.token_program,
+            &ctx.accounts.user,
+            &ctx.accounts.user_token,
+            &ctx.accounts.vault_token,
+            ctx.accounts.mint.key(),
+            deposit_amount,
+        )?;
+        vault_state.total_deposited += deposit_amount;
+        emit_deposit_accounted(
+            &ctx.accounts.config,
+            ctx.accounts.user.key(),
+            ctx.accounts.mint.key(),
+            deposit_amount,
+            vault_state.total_deposited,
+            &ctx.accounts.vault_token,
+        )?;
+
+        state.validate()?;
+        ctx.accounts
+            .config
+            .require_deadline_within_lifetime(&state, Clock::get()?.unix_timestamp)?;
+        require_keys_eq!(
+            state.token,
+            ctx.accounts.mint.key(),
+            ClearnetError::MintMismatch
+        );
+        require_keys_eq!(
+            state.wallet,
+            ctx.accounts.user.key(),
+            ClearnetError::WalletMismatch
+        );
+        require!(state.balance > 0, ClearnetError::EmptyState);
+        require!(
+            request_amount <= state.balance,
+            ClearnetError::InsufficientStateBalance
+        );
+        require!(
+            request_amount >= vault_state.min_withdraw,
+            ClearnetError::WithdrawTooSmall
+        );
+        vault_state.require_within_single_withdraw_cap(request_amount)?;
+        ctx.accounts
+            .config
+            .require_participants_within_cap(&state.participants)?;
+        let (node_accounts, _) =
+            split_participant_accounts(&state.participants, ctx.remaining_accounts)?;
+        verify_request_signatures(
+            &ctx.accounts.config,
+            &ctx.accounts.instructions,
+            node_accounts,
+            &state,
+            &ctx.accounts.user.key(),
+            request_amount,
+        )?;
+
+        ctx.accounts.request_account.bump = ctx.bumps.request_account;
+        store_request(
+            &ctx.accounts.user,
+            &mut ctx.accounts.request_account,
+            &mut ctx.accounts.config,
+            &mut vault_state,
+            &ctx.accounts.system_program,
+            &state,
+            request_amount,
+        )?;
+        save_vault_state(&ctx.accounts.vault_state, &vault_state)
+    }
+
+    /// Lets anyone escrow `amount` lamports on `wallet`'s pending request to attract
+    /// challengers. A successful `challenge` or `challenge_multi` that passes the incentive
+    /// account in `remaining_accounts`, after the node accounts, collects it; otherwise the
+    /// funder takes it back with `reclaim_challenge_incentive` once the request is gone.
+    pub fn add_challenge_incentive(ctx: Context<AddChallengeIncentive>, amount: u64) -> Result<()> {
+        let request = &ctx.accounts.request_account;
+        require!(request.expiration > 0, ClearnetError::NoPendingRequest);
+        let incentive = &mut ctx.accounts.incentive;
+        // Left over from an earlier request that nobody challenged
+        require!(
+            incentive.amount == 0 || incentive.height == request.height,
+            ClearnetError::StaleIncentive
+        );
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.funder.to_account_info(),
+                to: incentive.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_ctx, amount)?;
+        incentive.funder = ctx.accounts.funder.key();
+        incentive.wallet = request.wallet;
+        incentive.height = request.height;
+        incentive.amount += amount;
+
+        emit!(IncentiveAdded {
+            wallet: incentive.wallet,
+            height: incentive.height,
+            funder: incentive.funder,
+            amount,
+            total: incentive.amount,
+        });
+        Ok(())
+    }
+
+    /// Closes a challenge incentive back to its funder, once it was paid out or the request
+    /// it was escrowed for is no longer pending.
+    pub fn reclaim_challenge_incentive(ctx: Context<ReclaimChallengeIncentive>) -> Result<()> {
+        let incentive = &ctx.accounts.incentive;
+        let still_pending = load_request(&ctx.accounts.request_account)
+            .is_ok_and(|request| request.height == incentive.height);
+        require!(
+            incentive.amount == 0 || !still_pending,
+            ClearnetError::IncentiveLocked
+        );
+        Ok(())
+    }
+
+    /// Rejects the pending request with a newer `candidate`. `remaining_accounts` are the
+    /// `NodeEntry` accounts of `candidate.participants`, as for `request`, followed by any
+    /// `ChallengeIncentive` accounts to collect.
+    pub fn challenge(ctx: Context<Challenge>, candidate: State) -> Result<()> {
+        // Also enforced by `has_one = wallet`; asserted here so it doesn't hinge on field names.
+        require_keys_eq!(
+            ctx.accounts.wallet.key(),
+            ctx.accounts.request_account.wallet,
+            ClearnetError::WalletMismatch
+        );
+        candidate.validate()?;
+        ctx.accounts
+            .config
+            .require_deadline_within_lifetime(&candidate, Clock::get()?.unix_timestamp)?;
+        let req_acct = &ctx.accounts.request_account;
+
+        // 1. Verify existence of request
+        require!(req_acct.expiration > 0, ClearnetError::NoPendingRequest);
+        require_challenge_window_open(&ctx.accounts.config, req_acct)?;
+
+        // 2. Verify the candidate supersedes the requested state
+        require_supersedes(req_acct, &candidate)?;
+
+        // 3. Verify signatures
+        ctx.accounts
+            .config
+            .require_participants_within_cap(&candidate.participants)?;
+        let (node_accounts, incentives) =
+            split_participant_accounts(&candidate.participants, ctx.remaining_accounts)?;
+        verify_state_signatures(
+            &ctx.accounts.config,
+            &ctx.accounts.instructions,
+            node_accounts,
+            &candidate,
+        )?;
+
+        // 4. Close request (Reject)
+        // logic handled by `close` constraint or manual close?
+        // We want to delete the data. The `close` instruction handles transferring lamports.
+        // We just need to ensure the logic flows there.
+        // In Anchor, we can't conditionally close in the same instruction easily unless we use `close` constraint on a separate "Close" ix
+        // or manually realloc/assign.
+        // Standard pattern: Mark as invalid, or actually Close.
+        // We will close the account by sending lamports to the challenger.
+        pay_incentives(
+            incentives,
+            &ctx.accounts.request_account,
+            &ctx.accounts.challenger,
+        )?;
+        reject_request(
+            &ctx.accounts.request_account,
+            &ctx.accounts.wallet,
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.vault_state,
+            &candidate,
+        )
+    }
+
+    /// `challenge` with up to `MAX_CHALLENGE_CANDIDATES` states, for a challenger holding several
+    /// newer ones. Candidates that don't validate, aren't signed in the Ed25519Program
+    /// instruction right before this one, don't reach the node quorum, or don't supersede the
+    /// request are skipped; the request is rejected with the highest remaining one (at equal
+    /// heights, the lowest balance), as `challenge` would with it alone. `remaining_accounts`
+    /// hold each candidate's `NodeEntry` accounts in turn, then any `ChallengeIncentive`
+    /// accounts to collect.
+    pub fn challenge_multi(ctx: Context<ChallengeMulti>, candidates: Vec<State>) -> Result<()> {
+        require!(
+            candidates.len() <= MAX_CHALLENGE_CANDIDATES,
+            ClearnetError::TooManyCandidates
+        );
+        let req_acct = &ctx.accounts.request_account;
+        require!(req_acct.expiration > 0, ClearnetError::NoPendingRequest);
+        require_challenge_window_open(&ctx.accounts.config, req_acct)?;
+
+        let ix = preceding_ed25519_instruction(&ctx.accounts.instructions)?;
+        let now = Clock::get()?.unix_timestamp;
+        let mut best: Option<&State> = None;
+        let mut accounts = ctx.remaining_accounts;
+        for candidate in &candidates {
+            let (node_accounts, rest) =
+                split_participant_accounts(&candidate.participants, accounts)?;
+            accounts = rest;
+            let qualifies = candidate.validate().is_ok()
+                && candidate.wallet == req_acct.wallet
+                && ctx
+                    .accounts
+                    .config
+                    .require_deadline_within_lifetime(candidate, now)
+                    .is_ok()
+                && require_supersedes(req_acct, candidate).is_ok()
+                && ctx
+                    .accounts
+                    .config
+                    .require_participants_within_cap(&candidate.participants)
+                    .is_ok()
+                && prevalidate_signatures(&candidate.participants, &candidate.sigs).is_ok()
+                && state_signed(&ix.data, candidate)?
+                && require_quorum(
+                    &ctx.accounts.instructions,
+                    node_accounts,
+                    &hash_state(candidate)?,
+                    &ctx.accounts.config,
+                )
+                .is_ok();
+            let beats_best = best.is_none_or(|best| {
+                candidate.height > best.height
+                    || (candidate.height == best.height && candidate.balance < best.balance)
+            });
+            if qualifies && beats_best {
+                best = Some(candidate);
+            }
+        }
+        let winner = best.ok_or(ClearnetError::NoValidCandidate)?;
+
+        pay_incentives(
+            accounts,
+            &ctx.accounts.request_account,
+            &ctx.accounts.challenger,
+        )?;
+        reject_request(
+            &ctx.accounts.request_account,
+            &ctx.accounts.wallet,
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.vault_state,
+            winner,
+        )
+    }
+
+    /// The wallet's own `challenge` of its pending request with `candidate`, immediately
+    /// followed by a `request` of `amount` against it, so nobody can get in between. The
+    /// request account is reused: the old bounty is refunded and a new one escrowed.
+    /// `candidate` is checked both as a challenge and as a request, with its signatures in the
+    /// Ed25519Program instruction right before this one and its participants' `NodeEntry`
+    /// accounts in `remaining_accounts`.
+    pub fn challenge_and_request(
+        ctx: Context<ChallengeAndRequest>,
+        candidate: State,
+        amount: u64,
+    ) -> Result<()> {
+        candidate.validate()?;
+        ctx.accounts
+            .config
+            .require_deadline_within_lifetime(&candidate, Clock::get()?.unix_timestamp)?;
+        let old = (*ctx.accounts.request_account).clone();
+
+        // 1. Checks: the candidate rejects the pending request...
+        require!(old.expiration > 0, ClearnetError::NoPendingRequest);
+        require_challenge_window_open(&ctx.accounts.config, &old)?;
+        require_keys_eq!(
+            candidate.wallet,
+            ctx.accounts.user.key(),
+            ClearnetError::WalletMismatch
+        );
+        require_supersedes(&old, &candidate)?;
+        // ...and is good for a new one
+        require!(candidate.balance > 0, ClearnetError::EmptyState);
+        require!(
+            amount <= candidate.balance,
+            ClearnetError::InsufficientStateBalance
+        );
+        require!(
+            amount >= ctx.accounts.vault_state.min_withdraw,
+            ClearnetError::WithdrawTooSmall
+        );
+        ctx.accounts
+            .vault_state
+            .require_within_single_withdraw_cap(amount)?;
+        ctx.accounts
+            .config
+            .require_participants_within_cap(&candidate.participants)?;
+        // The amount message commits to `hash_state(candidate)`, so it proves the candidate
+        // for the challenge as well.
+        let (node_accounts, _) =
+            split_participant_accounts(&candidate.participants, ctx.remaining_accounts)?;
+        verify_request_signatures(
+            &ctx.accounts.config,
+            &ctx.accounts.instructions,
+            node_accounts,
+            &candidate,
+            &ctx.accounts.user.key(),
+            amount,
+        )?;
+
+        // 2. Reject the old request
+        ctx.accounts.config.close_request();
+        ctx.accounts.vault_state.pending_withdrawals = ctx
+            .accounts
+            .vault_state
+            .pending_withdrawals
+            .saturating_sub(old.amount);
+        if old.bounty > 0 {
+            ctx.accounts.request_account.sub_lamports(old.bounty)?;
+            ctx.accounts.user.add_lamports(old.bounty)?;
+            ensure_rent_exempt(
+                &ctx.accounts.request_account.to_account_info(),
+                &Rent::get()?,
+            )?;
+        }
+        emit_rejection(&ctx.accounts.config, &old, candidate.height)?;
+
+        // 3. Open the new one
+        store_request(
+            &ctx.accounts.user,
+            &mut ctx.accounts.request_account,
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.vault_state,
+            &ctx.accounts.system_program,
+            &candidate,
+            amount,
+        )?;
+        Ok(())
+    }
+
+    /// Pays out a request once its challenge period is over. A relayer may submit it on the
+    /// user's behalf if the transaction carries an Ed25519Program check of the user's signature
+    /// over `relay_withdraw_message`; the payout still goes to `user_token`.
+    ///
+    /// `finalize` is normally the requested state. Unless `strict_finalize_height` is set it may
+    /// instead be a newer one covering the amount, signed as for `request` (with its
+    /// participants' `NodeEntry` accounts in `remaining_accounts`); its height is then what gets
+    /// recorded as finalized.
+    pub fn withdraw(ctx: Context<Withdraw>, finalize: State) -> Result<()> {
+        // 1. Checks
+        let req_acct = check_withdraw(ctx.accounts, ctx.remaining_accounts, &finalize)?;
+        emit_instant_withdrawal(ctx.accounts, &req_acct)?;
+
+        // 2. Effects
+        let (wallet, token, amount) = (req_acct.wallet, req_acct.token, req_acct.amount);
+        consume_request(
+            &ctx.accounts.request_account,
+            &ctx.accounts.user,
+            &mut ctx.accounts.finalized,
+            finalize.height,
+        )?;
+        ctx.accounts.config.close_request();
+        // The fee stays in the vault until the fee recipient claims it.
+        let (fee, fee_exempt) = withdraw_fee(ctx.accounts, amount);
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.accrued_fees += fee;
+        vault_state.settle_withdrawal(amount, Clock::get()?.unix_timestamp);
+
+        // 3. Interactions
+        let sol_fee = charge_sol_fee(ctx.accounts)?;
+
+        // Seeds for signing
+        let bump = ctx.accounts.vault_state.bump;
+        let seeds = &[b"vault".as_ref(), token.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
+        vault_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault_token,
+            ctx.accounts.user_token.to_account_info(),
+            signer,
+            amount - fee,
+        )?;
+
+        emit_lifecycle(
+            &ctx.accounts.config,
+            RequestPhase::Withdrawn,
+            wallet,
+            token,
+            amount,
+            finalize.height,
+        )?;
+        if ctx.accounts.config.granular_events {
+            emit!(Withdrawn {
+                wallet,
+                token,
+                amount,
+                fee,
+                sol_fee,
+                fee_exempt,
+                recipient: ctx.accounts.user_token.key(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// `withdraw` paying the request out across several token accounts of its mint, passed
+    /// in `remaining_accounts` in the order of `portions` (ahead of any `NodeEntry` accounts a
+    /// newer `finalize` needs), whose amounts must add up to the requested amount. Each portion
+    /// is charged its own fee and gets its own `Withdrawn` event. The user must submit it
+    /// themselves: a relay signature only covers `user_token`.
+    pub fn withdraw_split<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Withdraw<'info>>,
+        finalize: State,
+        portions: Vec<SplitPortion>,
+    ) -> Result<()> {
+        // 1. Checks
+        require_keys_eq!(
+            ctx.accounts.relayer.key(),
+            ctx.accounts.user.key(),
+            ClearnetError::Unauthorized
+        );
+        let (recipient_accounts, node_accounts) = ctx
+            .remaining_accounts
+            .split_at(portions.len().min(ctx.remaining_accounts.len()));
+        let req_acct = check_withdraw(ctx.accounts, node_accounts, &finalize)?;
+        emit_instant_withdrawal(ctx.accounts, &req_acct)?;
+        require!(
+            portions.len() <= MAX_SPLIT_RECIPIENTS,
+            ClearnetError::TooManySplitRecipients
+        );
+        require!(
+            !portions.is_empty()
+                && portions.len() == recipient_accounts.len()
+                && portions
+                    .iter()
+                    .try_fold(0u64, |sum, portion| sum.checked_add(portion.amount))
+                    == Some(req_acct.amount),
+            ClearnetError::SplitMismatch
+        );
+        let mut recipients = Vec::with_capacity(portions.len());
+        for (portion, info) in portions.iter().zip(recipient_accounts) {
+            require_keys_eq!(info.key(), portion.recipient, ClearnetError::SplitMismatch);
+            let recipient = Account::<TokenAccount>::try_from(info)?;
+            require_keys_eq!(recipient.mint, req_acct.token, ClearnetError::MintMismatch);
+            recipients.push(info.clone());
+        }
+
+        // 2. Effects
+        let (wallet, token, amount) = (req_acct.wallet, req_acct.token, req_acct.amount);
+        consume_request(
+            &ctx.accounts.request_account,
+            &ctx.accounts.user,
+            &mut ctx.accounts.finalized,
+            finalize.height,
+        )?;
+        ctx.accounts.config.close_request();
+        let fees: Vec<_> = portions
+            .iter()
+            .map(|portion| withdraw_fee(ctx.accounts, portion.amount))
+            .collect();
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.accrued_fees += fees.iter().map(|(fee, _)| fee).sum::<u64>();
+        vault_state.settle_withdrawal(amount, Clock::get()?.unix_timestamp);
+
+        // 3. Interactions
+        let sol_fee = charge_sol_fee(ctx.accounts)?;
+        let bump = ctx.accounts.vault_state.bump;
+        let seeds = &[b"vault".as_ref(), token.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+        for (i, portion) in portions.iter().enumerate() {
+            let (fee, fee_exempt) = fees[i];
+            vault_transfer(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault_token,
+                recipients[i].clone(),
+                signer,
+                portion.amount - fee,
+            )?;
+            if ctx.accounts.config.granular_events {
+                emit!(Withdrawn {
+                    wallet,
+                    token,
+                    amount: portion.amount,
+                    fee,
+                    // Charged once, with the first portion
+                    sol_fee: if i == 0 { sol_fee } else { 0 },
+                    fee_exempt,
+                    recipient: portion.recipient,
+                });
+            }
+        }
+
+        emit_lifecycle(
+            &ctx.accounts.config,
+            RequestPhase::Withdrawn,
+            wallet,
+            token,
+            amount,
+            finalize.height,
+        )?;
+        Ok(())
+    }
+
+    /// Runs every check of `withdraw` with the same accounts and arguments, including the
+    /// vault's liquidity, without moving funds. Meant to be simulated before the real call:
+    /// returns the error code `withdraw` would fail with, or `0`.
+    pub fn dry_run_withdraw(ctx: Context<Withdraw>, finalize: State) -> Result<WithdrawDryRun> {
+        let error_code = match check_withdraw(ctx.accounts, ctx.remaining_accounts, &finalize) {
+            Ok(_) => 0,
+            Err(Error::AnchorError(err)) => err.error_code_number as u64,
+            Err(Error::ProgramError(err)) => u64::from(err.program_error),
+        };
+        Ok(WithdrawDryRun { error_code })
+    }
+
+    /// Finalizes a pending request immediately, skipping the challenge period.
+    ///
+    /// `finalize.sigs` must be fresh signatures by every participant over
+    /// `instant_withdraw_message(finalize, user_token, amount)`, checked through Ed25519Program
+    /// instructions in the same transaction. The participants' `NodeEntry` accounts go in
+    /// `remaining_accounts` in the same order as `finalize.participants`, and the nodes among
+    /// them must reach the quorum as for `request`. The higher `instant_fee_bps` applies, and
+    /// the height is recorded as finalized so neither path can pay it out again.
+    pub fn instant_withdraw(ctx: Context<InstantWithdraw>, finalize: State) -> Result<()> {
+        assert_operational(
+            &ctx.accounts.config,
+            &ctx.accounts.vault_state,
+            &ctx.accounts.blacklist,
+        )?;
+        let req_acct = load_request(&ctx.accounts.request_account)?;
+        let config = &ctx.accounts.config;
+
+        // 1. Checks
+        require!(
+            config.instant_fee_bps > 0,
+            ClearnetError::InstantWithdrawDisabled
+        );
+        require_keys_eq!(
+            ctx.accounts.mint.key(),
+            req_acct.token,
+            ClearnetError::MintMismatch
+        );
+        require_canonical_token_account(
+            &ctx.accounts.config,
+            &ctx.accounts.user_token.key(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.mint.key(),
+        )?;
+        require!(
+            req_acct.amount >= ctx.accounts.vault_state.min_withdraw,
+            ClearnetError::WithdrawTooSmall
+        );
+        ctx.accounts
+            .vault_state
+            .require_within_single_withdraw_cap(req_acct.amount)?;
+        ctx.accounts
+            .vault_state
+            .require_within_daily_withdraw_limit(req_acct.amount, Clock::get()?.unix_timestamp)?;
+        require!(
+            finalize.wallet == req_acct.wallet
+                && finalize.token == req_acct.token
+                && finalize.height == req_acct.height,
+            ClearnetError::StateMismatch
+        );
+        require!(
+            finalize.height > ctx.accounts.finalized.last_height,
+            ClearnetError::HeightAlreadyFinalized
+        );
+
+        // 2. Verify the quorum co-signed this exact payout
+        let amount = req_acct.amount;
+        let (node_accounts, _) =
+            split_participant_accounts(&finalize.participants, ctx.remaining_accounts)?;
+        let message = instant_withdraw_message(&finalize, &ctx.accounts.user_token.key(), amount)?;
+        verify_ed25519_signatures(
+            &ctx.accounts.instructions,
+            &finalize.participants,
+            &finalize.sigs,
+            &message,
+        )?;
+        require_quorum(&ctx.accounts.instructions, node_accounts, &message, config)?;
+
+        // 3. Effects
+        let (wallet, token) = (req_acct.wallet, req_acct.token);
+        let fee_exempt = !ctx.accounts.fee_exemption.data_is_empty();
+        let fee = if fee_exempt {
+            0
+        } else {
+            compute_fee(amount, config.instant_fee_bps, config.fee_round_up)
+        };
+        consume_request(
+            &ctx.accounts.request_account,
+            &ctx.accounts.user,
+            &mut ctx.accounts.finalized,
+            req_acct.height,
+        )?;
+        ctx.accounts.config.close_request();
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.accrued_fees += fee;
+        vault_state.settle_withdrawal(amount, Clock::get()?.unix_timestamp);
+
+        // 4. Interactions
+        let bump = ctx.accounts.vault_state.bump;
+        let seeds = &[b"vault".as_ref(), token.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
+        vault_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault_token,
+            ctx.accounts.user_token.to_account_info(),
+            signer,
+            amount - fee,
+        )?;
+
+        emit_lifecycle(
+            &ctx.accounts.config,
+            RequestPhase::Withdrawn,
+            wallet,
+            token,
+            amount,
+            finalize.height,
+        )?;
+        if ctx.accounts.config.granular_events {
+            emit!(Withdrawn {
+                wallet,
+                token,
+                amount,
+                fee,
+                sol_fee: 0,
+                fee_exempt,
+                recipient: ctx.accounts.user_token.key(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Closes a request left unclaimed for `sweep_delay` seconds after its challenge period,
+    /// freeing its slot under `max_open_requests`. The sweeper earns `sweep_reward` (or all
+    /// the account holds, if less) out of the request's rent and bounty, the rest goes back to
+    /// the wallet, and the funds stay in the vault for a later request. Restricted to active
+    /// nodes unless `sweep_permissionless` is set.
+    pub fn sweep_expired_request(ctx: Context<SweepExpiredRequest>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        if !config.sweep_permissionless {
+            require!(
+                ctx.accounts
+                    .node_entry
+                    .as_ref()
+                    .is_some_and(|node| node.is_active),
+                ClearnetError::Unauthorized
+            );
+        }
+
+        let req_acct = &ctx.accounts.request_account;
+        require!(
+            Clock::get()?.unix_timestamp >= req_acct.expiration + config.sweep_delay,
+            ClearnetError::RequestNotExpired
+        );
+        config.close_request();
+        // The funds stay deposited; only the claim on them lapses.
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.pending_withdrawals = vault_state
+            .pending_withdrawals
+            .saturating_sub(req_acct.amount);
+        // Taken before `close = wallet` hands the remaining lamports to the wallet.
+        let reward = config.sweep_reward.min(req_acct.get_lamports());
+        req_acct.sub_lamports(reward)?;
+        ctx.accounts.sweeper.add_lamports(reward)?;
+
+        // A swept request is cancelled: it leaves without a payout or a rejection.
+        emit_lifecycle(
+            config,
+            RequestPhase::Cancelled,
+            req_acct.wallet,
+            req_acct.token,
+            req_acct.amount,
+            req_acct.height,
+        )?;
+        if config.granular_events {
+            emit!(RequestSwept {
+                sweeper: ctx.accounts.sweeper.key(),
+                wallet: req_acct.wallet,
+                token: req_acct.token,
+                amount: req_acct.amount,
+                reward,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Closes a pending request on the strength of its wallet's own Ed25519 signature over
+    /// `revoke_request_message(wallet, token, height, expiration)`, at any point before payout.
+    /// The expiration ties the signature to this one request, so it can't revoke a later
+    /// request of the same state. Anyone may
+    /// submit it, so a user whose state was compromised can cancel through a relayer; rent and
+    /// bounty return to the wallet.
+    pub fn revoke_request(ctx: Context<RevokeRequest>, revocation_sig: [u8; 64]) -> Result<()> {
+        let req_acct = &ctx.accounts.request_account;
+        let message = revoke_request_message(
+            &req_acct.wallet,
+            &req_acct.token,
+            req_acct.height,
+            req_acct.expiration,
+        );
+        verify_ed25519_signatures(
+            &ctx.accounts.instructions,
+            &[req_acct.wallet],
+            &[revocation_sig.to_vec()],
+            &message,
+        )?;
+
+        let config = &mut ctx.accounts.config;
+        config.close_request();
+        let vault_state = &mut ctx.accounts.vault_state;
+        vault_state.pending_withdrawals = vault_state
+            .pending_withdrawals
+            .saturating_sub(req_acct.amount);
+
+        emit_lifecycle(
+            config,
+            RequestPhase::Cancelled,
+            req_acct.wallet,
+            req_acct.token,
+            req_acct.amount,
+            req_acct.height,
+        )?;
+        if config.granular_events {
+            emit!(RequestRevoked {
+                wallet: req_acct.wallet,
+                token: req_acct.token,
+                amount: req_acct.amount,
+                height: req_acct.height,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Pays the fees accrued for `vault_state`'s mint to the current fee recipient.
+    pub fn claim_fees(ctx: Context<ClaimFees>) -> Result<()> {
+        let vault_state = &mut ctx.accounts.vault_state;
+        let amount = vault_state.accrued_fees;
+        require!(amount > 0, ClearnetError::NoFeesToClaim);
+        vault_state.accrued_fees = 0;
+
+        let seeds = &[
+            b"vault".as_ref(),
+            vault_state.mint.as_ref(),
+            &[vault_state.bump],
+        ];
+        vault_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault_token,
+            ctx.accounts.fee_token.to_account_info(),
+            &[&seeds[..]],
+            amount,
+        )?;
+
+        emit!(FeesClaimed {
+            mint: vault_state.mint,
+            recipient: ctx.accounts.fee_recipient.key(),
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Emergency move of the whole vault balance for `vault_state`'s mint to `new_vault`, e.g. a
+    /// token account owned by an upgraded program. Only allowed while the vault or the token is
+    /// paused. The bookkeeping (`total_deposited`, pending withdrawals, fees) is left as is, so
+    /// the new custodian can take it over.
+    pub fn migrate_vault(ctx: Context<MigrateVault>) -> Result<()> {
+        require_admin_threshold(
+            &ctx.accounts.config,
+            &ctx.accounts.admin,
+            ctx.remaining_accounts,
+        )?;
+        let vault_state = &ctx.accounts.vault_state;
+        require!(
+            ctx.accounts.config.paused || vault_state.paused,
+            ClearnetError::NotPaused
+        );
+
+        let amount = ctx.accounts.vault_token.amount;
+        let seeds = &[
+            b"vault".as_ref(),
+            vault_state.mint.as_ref(),
+            &[vault_state.bump],
+        ];
+        vault_transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault_token,
+            ctx.accounts.new_vault.to_account_info(),
+            &[&seeds[..]],
+            amount,
+        )?;
+
+        emit!(VaultMigrated {
+            mint: vault_state.mint,
+            new_vault: ctx.accounts.new_vault.key(),
+            amount,
+            total_deposited: vault_state.total_deposited,
+            ts: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+// --- Helpers ---
+
+/// Hash of the signed preimage of a `State`, laid out according to its `state_version`:
+/// - v1: wallet, token, height, balance and participants, Borsh-encoded, with participants
+///   sorted so the hash doesn't depend on the order signatures were collected in.
+/// - v2: the version byte and this program's id, followed by the v1 fields, so a state signed
+///   for one deployment can't be replayed against another.
+///
+/// In either layout a non-zero `da_hash`, then a non-zero `deadline`, are appended last.
+pub fn hash_state(state: &State) -> Result<[u8; 32]> {
+    StateVersion::try_from(state.state_version)?;
+    Ok(hash(&encode_state_for_signing(state)).to_bytes())
+}
+
+/// The exact preimage `hash_state` hashes, for off-chain signers to depend on instead of
+/// re-implementing the layout. `sigs` are never part of it and participants are sorted.
+///
+/// Any `state_version` other than v1 gets the v2 layout; check it with `State::validate` (or
+/// let `hash_state` reject it) before signing.
+///
+/// ```
+/// use anchor_lang::prelude::Pubkey;
+/// use clearnet::{encode_state_for_signing, hash_state, State};
+/// use solana_sha256_hasher::hash;
+///
+/// let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+/// let state = State::new(Pubkey::new_unique(), Pubkey::new_unique(), 3, 100, vec![b, a], vec![]);
+/// let encoded = encode_state_for_signing(&state);
+/// assert_eq!(hash(&encoded).to_bytes(), hash_state(&state).unwrap());
+///
+/// // Independent of signatures and of the order participants were listed in
+/// let reordered = State::new(state.wallet, state.token, 3, 100, vec![a, b], vec![vec![0; 64]]);
+/// assert_eq!(encode_state_for_signing(&reordered), encoded);
+///
+/// // A DA hash is only part of it when set
+/// let with_da = state.clone().with_da_hash([7; 32]);
+/// assert_eq!(encode_state_for_signing(&with_da), [encoded.as_slice(), &[7; 32]].concat());
+/// assert_eq!(encode_state_for_signing(&state.clone().with_da_hash([0; 32])), encoded);
+///
+/// // So is a deadline
+/// let with_deadline = state.with_deadline(1_700_000_000);
+/// assert_eq!(
+///     encode_state_for_signing(&with_deadline),
+///     [encoded.as_slice(), &1_700_000_000i64.to_le_bytes()].concat()
+/// );
+/// ```
+pub fn encode_state_for_signing(state: &State) -> Vec<u8> {
+    let mut participants = state.participants.clone();
+    participants.sort();
+
+    let mut preimage = Vec::with_capacity(1 + 32 + 32 + 32 + 8 + 8 + 4 + 32 * participants.len());
+    if state.state_version != StateVersion::V1 as u8 {
+        preimage.push(state.state_version);
+        preimage.extend_from_slice(crate::ID.as_ref());
+    }
+    preimage.extend_from_slice(state.wallet.as_ref());
+    preimage.extend_from_slice(state.token.as_ref());
+    preimage.extend_from_slice(&state.height.to_le_bytes());
+    preimage.extend_from_slice(&state.balance.to_le_bytes());
+    preimage.extend_from_slice(&(participants.len() as u32).to_le_bytes());
+    for participant in &participants {
+        preimage.extend_from_slice(participant.as_ref());
+    }
+    if state.da_hash != [0; 32] {
+        preimage.extend_from_slice(&state.da_hash);
+    }
+    if state.deadline != 0 {
+        preimage.extend_from_slice(&state.deadline.to_le_bytes());
+    }
+    preimage
+}
+
+/// Message nodes sign to approve paying `amount` of `state` to `recipient` without waiting.
+pub fn instant_withdraw_message(
+    state: &State,
+    recipient: &Pubkey,
+    amount: u64,
+) -> Result<[u8; 32]> {
+    let mut preimage = Vec::with_capacity(7 + 32 + 32 + 8);
+    preimage.extend_from_slice(b"instant");
+    preimage.extend_from_slice(&hash_state(state)?);
+    preimage.extend_from_slice(recipient.as_ref());
+    preimage.extend_from_slice(&amount.to_le_bytes());
+    Ok(hash(&preimage).to_bytes())
+}
+
+/// Message nodes sign, with `sign_amounts` on, to authorize a request of exactly `amount` of
+/// `state` by `recipient`.
+pub fn withdrawal_amount_message(
+    state: &State,
+    recipient: &Pubkey,
+    amount: u64,
+) -> Result<[u8; 32]> {
+    let mut preimage = Vec::with_capacity(6 + 32 + 32 + 8);
+    preimage.extend_from_slice(b"amount");
+    preimage.extend_from_slice(&hash_state(state)?);
+    preimage.extend_from_slice(recipient.as_ref());
+    preimage.extend_from_slice(&amount.to_le_bytes());
+    Ok(hash(&preimage).to_bytes())
+}
+
+/// Message nodes sign to confirm the deposit `deposit_ref` at ledger `height`.
+pub fn deposit_confirmation_message(deposit_ref: &[u8; 32], height: u64) -> [u8; 32] {
+    let mut preimage = Vec::with_capacity(7 + 32 + 32 + 8);
+    preimage.extend_from_slice(b"deposit");
+    preimage.extend_from_slice(crate::ID.as_ref());
+    preimage.extend_from_slice(deposit_ref);
+    preimage.extend_from_slice(&height.to_le_bytes());
+    hash(&preimage).to_bytes()
+}
+
+/// Message a wallet signs to revoke its pending request at `height` for `token`, identified
+/// by the request's `expiration`.
+pub fn revoke_request_message(
+    wallet: &Pubkey,
+    token: &Pubkey,
+    height: u64,
+    expiration: i64,
+) -> [u8; 32] {
+    let mut preimage = Vec::with_capacity(6 + 32 + 32 + 32 + 8 + 8);
+    preimage.extend_from_slice(b"revoke");
+    preimage.extend_from_slice(crate::ID.as_ref());
+    preimage.extend_from_slice(wallet.as_ref());
+    preimage.extend_from_slice(token.as_ref());
+    preimage.extend_from_slice(&height.to_le_bytes());
+    preimage.extend_from_slice(&expiration.to_le_bytes());
+    hash(&preimage).to_bytes()
+}
+
+/// Message a user signs to let a relayer submit `withdraw` of `state` for them, paying out to
+/// `user_token` only.
+pub fn relay_withdraw_message(state: &State, user_token: &Pubkey) -> Result<[u8; 32]> {
+    let mut preimage = Vec::with_capacity(5 + 32 + 32);
+    preimage.extend_from_slice(b"relay");
+    preimage.extend_from_slice(&hash_state(state)?);
+    preimage.extend_from_slice(user_token.as_ref());
+    Ok(hash(&preimage).to_bytes())
+}
+
+/// Deserializes a `NodeEntry` owned by this program, or `None` for any other account.
+fn load_node_entry(account: &AccountInfo) -> Option<NodeEntry> {
+    if account.owner != &crate::ID {
+        return None;
+    }
+    NodeEntry::try_deserialize(&mut &account.data.borrow()[..]).ok()
+}
+
+/// Number and total weight of the distinct active (and, with a `heartbeat_window`, live) nodes
+/// holding at least `node_bond` and past their `activation_grace` among `node_accounts` that
+/// signed `message` in an Ed25519Program instruction of this transaction.
+fn signed_node_totals(
+    instructions: &AccountInfo,
+    node_accounts: &[AccountInfo],
+    message: &[u8],
+    config: &VaultConfig,
+) -> Result<(u64, u64)> {
+    let now = Clock::get()?.unix_timestamp;
+    let heartbeat_window = config.heartbeat_window;
+    let mut counted: Vec<Pubkey> = Vec::with_capacity(node_accounts.len());
+    let mut weight = 0u64;
+    for entry in node_accounts.iter().filter_map(load_node_entry) {
+        if !entry.is_active
+            || entry.bond < config.node_bond
+            || now < entry.activated_at.saturating_add(config.activation_grace)
+            || (heartbeat_window != 0 && !entry.is_live(heartbeat_window, now))
+            || counted.contains(&entry.authority)
+            || !ed25519_signed(instructions, &entry.authority, message)
+        {
+            continue;
+        }
+        counted.push(entry.authority);
+        weight += entry.weight;
+    }
+    Ok((counted.len() as u64, weight))
+}
+
+/// Splits `accounts` into the `NodeEntry` accounts of `participants`, in the same order, and
+/// whatever follows them. Participants that aren't registered nodes still need their (empty)
+/// node PDA at their position; an account at the wrong position, or too few accounts, fails
+/// with `ParticipantAccountMismatch`.
+fn split_participant_accounts<'a, 'info>(
+    participants: &[Pubkey],
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<(&'a [AccountInfo<'info>], &'a [AccountInfo<'info>])> {
+    require!(
+        accounts.len() >= participants.len(),
+        ClearnetError::ParticipantAccountMismatch
+    );
+    let (nodes, rest) = accounts.split_at(participants.len());
+    for (participant, account) in participants.iter().zip(nodes) {
+        let (expected, _) =
+            Pubkey::find_program_address(&[b"node", participant.as_ref()], &crate::ID);
+        require_keys_eq!(
+            account.key(),
+            expected,
+            ClearnetError::ParticipantAccountMismatch
+        );
+    }
+    Ok((nodes, rest))
+}
+
+/// Requires the nodes among `node_accounts` that signed `message` (as counted by
+/// `signed_node_totals`) to be at least `min_signers` and to reach quorum of the active set by
+/// count and by weight. Returns their number and total weight.
+fn require_quorum(
+    instructions: &AccountInfo,
+    node_accounts: &[AccountInfo],
+    message: &[u8],
+    config: &VaultConfig,
+) -> Result<(u64, u64)> {
+    let (count, weight) = signed_node_totals(instructions, node_accounts, message, config)?;
+    // Weight alone could be carried by one heavy node.
+    require!(
+        count >= config.min_signers as u64,
+        ClearnetError::TooFewSigners
+    );
+    require!(
+        count > 0
+            && count >= quorum_of(config.active_node_count as u64)
+            && weight >= quorum_of(config.active_node_weight),
+        ClearnetError::InsufficientQuorum
+    );
+    Ok((count, weight))
+}
+
+/// Requires that `sigs[i]` by `participants[i]` over `message` was checked by an Ed25519Program
+/// instruction in the current transaction.
+///
+/// The Ed25519 program aborts the transaction on an invalid signature, so finding a matching
+/// `(pubkey, signature, message)` entry in one of its instructions proves the signature.
+fn verify_ed25519_signatures(
+    instructions: &AccountInfo,
+    participants: &[Pubkey],
+    sigs: &[Vec<u8>],
+    message: &[u8],
+) -> Result<()> {
+    prevalidate_signatures(participants, sigs)?;
+
+    for (participant, sig) in participants.iter().zip(sigs) {
+        let mut verified = false;
+        let mut index = 0;
+        while let Ok(ix) = ix_sysvar::load_instruction_at_checked(index, instructions) {
+            index += 1;
+            if ix.program_id != ed25519_program::ID {
+                continue;
+            }
+            verified = ed25519_entries(&ix.data).any(|(pubkey, signature, msg)| {
+                pubkey == participant.as_ref() && signature == sig.as_slice() && msg == message
+            });
+            if verified {
+                break;
+            }
+        }
+        require!(verified, ClearnetError::InvalidSignature);
+    }
+    Ok(())
+}
+
+/// Whether an Ed25519Program instruction in the current transaction checked a signature by
+/// `signer` over `message`. The signature bytes don't matter: the Ed25519 program aborts the
+/// transaction if any of its entries is invalid.
+fn ed25519_signed(instructions: &AccountInfo, signer: &Pubkey, message: &[u8]) -> bool {
+    let mut index = 0;
+    while let Ok(ix) = ix_sysvar::load_instruction_at_checked(index, instructions) {
+        index += 1;
+        if ix.program_id == ed25519_program::ID
+            && ed25519_entries(&ix.data)
+                .any(|(pubkey, _, msg)| pubkey == signer.as_ref() && msg == message)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Yields the `(pubkey, signature, message)` entries of an Ed25519Program instruction whose
+/// data is fully contained in the instruction itself. Malformed entries are skipped.
+fn ed25519_entries(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8], &[u8])> {
+    const HEADER: usize = 2;
+    const OFFSETS: usize = 14;
+    const SELF: u16 = u16::MAX;
+
+    let count = data.first().copied().unwrap_or(0) as usize;
+    (0..count).filter_map(move |i| {
+        let start = HEADER + i * OFFSETS;
+        let offsets = data.get(start..start + OFFSETS)?;
+        let field = |n: usize| u16::from_le_bytes([offsets[2 * n], offsets[2 * n + 1]]);
+
+        let (sig_offset, sig_ix) = (field(0) as usize, field(1));
+        let (key_offset, key_ix) = (field(2) as usize, field(3));
+        let (msg_offset, msg_size, msg_ix) = (field(4) as usize, field(5) as usize, field(6));
+        if sig_ix != SELF || key_ix != SELF || msg_ix != SELF {
+            return None;
+        }
+
+        Some((
+            data.get(key_offset..key_offset + 32)?,
+            data.get(sig_offset..sig_offset + 64)?,
+            data.get(msg_offset..msg_offset + msg_size)?,
+        ))
+    })
+}
+
+/// Builds the Ed25519Program instruction that checks `sigs[i]` by `participants[i]` over
+/// `messages[i]`, with every offset pointing into the instruction itself as the program's
+/// introspection requires. For `request`/`challenge`, pass `hash_state(state)` for every
+/// participant and place the instruction immediately before the program instruction.
+///
+/// Panics if the three slices differ in length, a signature isn't 64 bytes or the data
+/// outgrows the `u16` offsets.
+///
+/// ```
+/// use anchor_lang::prelude::Pubkey;
+/// use clearnet::{build_ed25519_verify_ix, ed25519_verify_entries};
+///
+/// let participants = [Pubkey::new_unique(), Pubkey::new_unique()];
+/// let messages = [vec![1; 32], vec![2; 40]];
+/// let sigs = [vec![3; 64], vec![4; 64]];
+/// let ix = build_ed25519_verify_ix(&participants, &messages, &sigs);
+///
+/// // Parsed back exactly as the program parses it on-chain
+/// let expected: Vec<_> = (0..2)
+///     .map(|i| (participants[i], sigs[i].clone(), messages[i].clone()))
+///     .collect();
+/// assert_eq!(ed25519_verify_entries(&ix.data), expected);
+/// ```
+#[cfg(feature = "client")]
+pub fn build_ed25519_verify_ix(
+    participants: &[Pubkey],
+    messages: &[Vec<u8>],
+    sigs: &[Vec<u8>],
+) -> Instruction {
+    const HEADER: usize = 2;
+    const OFFSETS: usize = 14;
+    const SELF: u16 = u16::MAX;
+
+    assert_eq!(participants.len(), messages.len());
+    assert_eq!(participants.len(), sigs.len());
+    let count = u8::try_from(participants.len()).expect("too many signatures");
+    let offset = |n: usize| u16::try_from(n).expect("Ed25519 instruction too large");
+
+    let mut offsets = Vec::with_capacity(participants.len() * OFFSETS);
+    let mut payload = Vec::new();
+    let mut next = HEADER + participants.len() * OFFSETS;
+    for ((participant, message), sig) in participants.iter().zip(messages).zip(sigs) {
+        assert_eq!(sig.len(), 64, "signatures are 64 bytes");
+        let (key_offset, sig_offset, msg_offset) = (next, next + 32, next + 96);
+        next = msg_offset + message.len();
+        for field in [
+            offset(sig_offset),
+            SELF,
+            offset(key_offset),
+            SELF,
+            offset(msg_offset),
+            offset(message.len()),
+            SELF,
+        ] {
+            offsets.extend_from_slice(&field.to_le_bytes());
+        }
+        payload.extend_from_slice(participant.as_ref());
+        payload.extend_from_slice(sig);
+        payload.extend_from_slice(message);
+    }
+
+    let mut data = vec![count, 0];
+    data.extend(offsets);
+    data.extend(payload);
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: vec![],
+        data,
+    }
+}
+
+/// The `(pubkey, signature, message)` entries the program reads from Ed25519Program
+/// instruction data, for clients to check an instruction before sending it.
+#[cfg(feature = "client")]
+pub fn ed25519_verify_entries(data: &[u8]) -> Vec<(Pubkey, Vec<u8>, Vec<u8>)> {
+    ed25519_entries(data)
+        .filter_map(|(pubkey, sig, msg)| {
+            Some((Pubkey::try_from(pubkey).ok()?, sig.to_vec(), msg.to_vec()))
+        })
+        .collect()
+}
+
+/// Takes an active node out of the active set and moves its bond into the exit escrow, as
+/// `exit_node` does.
+fn retire_node(
+    config: &mut Account<VaultConfig>,
+    node_entry: &mut Account<NodeEntry>,
+    escrow: &mut Account<ExitingBond>,
+) -> Result<()> {
+    config.track_node(node_entry.weight, true, false);
+    node_entry.is_active = false;
+    node_entry.deactivated_at = Clock::get()?.unix_timestamp;
+    emit_node_set_changed(config, node_entry.authority, node_entry.deactivated_at);
+
+    escrow.authority = node_entry.authority;
+    let bond = node_entry.bond;
+    let unlock_ts = escrow_bond(node_entry, escrow, config.dispute_window)?;
+
+    emit!(NodeExited {
+        node: node_entry.authority,
+        bond,
+        unlock_ts,
+    });
+
+    Ok(())
+}
+
+/// Moves `node_entry`'s bond into `escrow`, where it stays slashable for `dispute_window`
+/// seconds instead of becoming withdrawable right away. Returns the new unlock time.
+fn escrow_bond(
+    node_entry: &mut Account<NodeEntry>,
+    escrow: &mut Account<ExitingBond>,
+    dispute_window: i64,
+) -> Result<i64> {
+    let bond = node_entry.bond;
+    let unlock_ts = Clock::get()?.unix_timestamp + dispute_window;
+    node_entry.sub_lamports(bond)?;
+    ensure_rent_exempt(&node_entry.to_account_info(), &Rent::get()?)?;
+    escrow.add_lamports(bond)?;
+    escrow.amount += bond;
+    escrow.unlock_ts = unlock_ts;
+    node_entry.bond = 0;
+
+    emit!(NodeBondEscrowed {
+        node: escrow.authority,
+        amount: escrow.amount,
+        unlock_ts,
+    });
+
+    Ok(unlock_ts)
+}
+
+/// Transfers `amount` of `user`'s tokens into the vault and emits `Deposited` via
+/// `emit_deposited`.
+fn transfer_to_vault<'info>(
+    config: &VaultConfig,
+    token_program: &Program<'info, Token>,
+    user: &Signer<'info>,
+    user_token: &Account<'info, TokenAccount>,
+    vault_token: &UncheckedAccount<'info>,
+    mint: Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Transfer {
+        from: user_token.to_account_info(),
+        to: vault_token.to_account_info(),
+        authority: user.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_ctx, amount)?;
+
+    emit_deposited(config, user.key(), mint, amount);
+    Ok(())
+}
+
+/// Emits `NodeSetChanged` after `node` left the active set. Requests opened before then may
+/// carry its signature, so watchers should re-check every open request against the new set.
+fn emit_node_set_changed(config: &VaultConfig, node: Pubkey, ts: i64) {
+    emit!(NodeSetChanged {
+        node,
+        active_node_count: config.active_node_count,
+        open_request_count: config.open_request_count,
+        ts,
+    });
+}
+
+/// Emits `Deposited` unless `amount` is below `min_event_amount`. Deposits are booked in
+/// `VaultState` either way, so the event is only a notification and the ledger stays
+/// authoritative.
+fn emit_deposited(config: &VaultConfig, wallet: Pubkey, token: Pubkey, amount: u64) {
+    if amount >= config.min_event_amount {
+        emit!(Deposited {
+            wallet,
+            token,
+            amount,
+        });
+    }
+}
+
+/// Emits `DepositAccounted` when `deposit_accounting_events` is on. Called once the deposit
+/// is booked and transferred, so the totals it carries are the ones now on chain. Unlike
+/// `Deposited` it ignores `min_event_amount`: a ledger needs every deposit.
+fn emit_deposit_accounted(
+    config: &VaultConfig,
+    wallet: Pubkey,
+    mint: Pubkey,
+    amount: u64,
+    total_deposited: u64,
+    vault_token: &AccountInfo,
+) -> Result<()> {
+    if config.deposit_accounting_events {
+        let vault = TokenAccount::try_deserialize(&mut &vault_token.data.borrow()[..])?;
+        let clock = Clock::get()?;
+        emit!(DepositAccounted {
+            wallet,
+            mint,
+            amount,
+            total_deposited,
+            vault_balance: vault.amount,
+            slot: clock.slot,
+            ts: clock.unix_timestamp,
+        });
+    }
+    Ok(())
+}
+
+/// Records a validated withdrawal request for `state`, starts its challenge period, escrows
+/// the configured challenge bounty and emits the request events. Returns the request's
+/// expiration and challenge period.
+fn store_request<'info>(
+    user: &Signer<'info>,
+    req_acct: &mut Account<'info, WithdrawalRequest>,
+    config: &mut Account<'info, VaultConfig>,
+    vault_state: &mut VaultState,
+    system_program: &Program<'info, System>,
+    state: &State,
+    amount: u64,
+) -> Result<RequestReceipt> {
+    req_acct.wallet = state.wallet;
+    req_acct.token = state.token;
+    req_acct.amount = amount;
+    req_acct.height = state.height;
+    req_acct.balance = state.balance;
+    req_acct.da_hash = state.da_hash;
+    let challenge_period = vault_state.challenge_period_for(config, amount);
+    let expiration = Clock::get()?.unix_timestamp + challenge_period;
+    req_acct.expiration = expiration;
+    let bounty = config.challenge_bounty;
+    req_acct.bounty = bounty;
+    config.open_request()?;
+    vault_state.pending_withdrawals += amount;
+
+    // Escrow the bounty in the request account on top of its rent. A successful challenge
+    // closes the account to the challenger; a payout closes it back to the user.
+    if bounty > 0 {
+        let cpi_ctx = CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: user.to_account_info(),
+                to: req_acct.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_ctx, bounty)?;
+    }
+
+    emit_lifecycle(
+        config,
+        RequestPhase::Requested,
+        state.wallet,
+        state.token,
+        amount,
+        state.height,
+    )?;
+    if config.granular_events {
+        // `Challenged` carries what watchers need; `Requested` is only for verbose deployments.
+        if config.verbose_events {
+            emit!(Requested {
+                wallet: state.wallet,
+                token: state.token,
+                amount,
+            });
+        }
+
+        emit!(Challenged {
+            wallet: state.wallet,
+            height: state.height,
+            expiration,
+            challenge_period,
+        });
+    }
+
+    Ok(RequestReceipt {
+        expiration,
+        height: state.height,
+        challenge_period,
+    })
+}
+
+/// Pays `challenger` every `ChallengeIncentive` among `incentives` escrowed on `request`'s
+/// wallet and height. Other accounts are skipped; paid incentives stay open, empty, for their
+/// funders to reclaim the rent.
+fn pay_incentives(
+    incentives: &[AccountInfo],
+    request: &WithdrawalRequest,
+    challenger: &AccountInfo,
+) -> Result<()> {
+    for info in incentives {
+        if info.owner != &crate::ID {
+            continue;
+        }
+        let Ok(mut incentive) = ChallengeIncentive::try_deserialize(&mut &info.data.borrow()[..])
+        else {
+            continue;
+        };
+        if incentive.wallet != request.wallet
+            || incentive.height != request.height
+            || incentive.amount == 0
+        {
+            continue;
+        }
+
+        let amount = incentive.amount;
+        info.sub_lamports(amount)?;
+        ensure_rent_exempt(info, &Rent::get()?)?;
+        challenger.add_lamports(amount)?;
+        incentive.amount = 0;
+        incentive.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])?;
+        emit!(IncentivePaid {
+            wallet: incentive.wallet,
+            height: incentive.height,
+            funder: incentive.funder,
+            challenger: challenger.key(),
+            amount,
+        });
+    }
+    Ok(())
+}
+
+/// Books the rejection of `request_account` by `candidate`: frees its slot and pending amount,
+/// refunds the unearned part of the bounty and emits the rejection events. The account itself
+/// is closed to the challenger by the instruction's `close` constraint.
+fn reject_request<'info>(
+    request_account: &Account<'info, WithdrawalRequest>,
+    wallet: &AccountInfo<'info>,
+    config: &mut VaultConfig,
+    vault_state: &mut VaultState,
+    candidate: &State,
+) -> Result<()> {
+    config.close_request();
+    vault_state.pending_withdrawals = vault_state
+        .pending_withdrawals
+        .saturating_sub(request_account.amount);
+
+    // A same-height proof only shows the balance was inflated by part of it, so the
+    // challenger earns that share of the bounty and the rest goes back to the user.
+    // A newer state or a DA fork rejects the request outright and keeps the full bounty.
+    if candidate.height == request_account.height
+        && !proves_da_fork(request_account, candidate)
+        && request_account.bounty > 0
+    {
+        let share = reduction_bounty_share(
+            request_account.bounty,
+            request_account.balance,
+            candidate.balance,
+        );
+        let refund = request_account.bounty - share;
+        request_account.sub_lamports(refund)?;
+        wallet.add_lamports(refund)?;
+    }
+
+    emit_rejection(config, request_account, candidate.height)
+}
+
+/// Requires `candidate` to supersede the state `request` was opened with: either it is newer,
+/// or it has the same height with a lower balance, proving the requested balance was inflated,
+/// or with a different DA commitment, proving the request is on a fork. A state for another
+/// token says nothing about the request.
+fn require_supersedes(request: &WithdrawalRequest, candidate: &State) -> Result<()> {
+    require!(
+        candidate.token == request.token,
+        ClearnetError::StateMismatch
+    );
+    if candidate.height == request.height {
+        require!(
+            candidate.balance < request.balance || proves_da_fork(request, candidate),
+            ClearnetError::NoFraudProven
+        );
+    } else {
+        require!(
+            candidate.height > request.height,
+            ClearnetError::CandidateNotNewer
+        );
+    }
+    Ok(())
+}
+
+/// Whether `candidate`, at `request`'s height, commits to other off-chain data than the
+/// requested state did. A candidate without a DA hash proves nothing about it.
+fn proves_da_fork(request: &WithdrawalRequest, candidate: &State) -> bool {
+    candidate.height == request.height
+        && candidate.da_hash != [0; 32]
+        && candidate.da_hash != request.da_hash
+}
+
+/// Requires `request` can still be challenged: up to and including its expiration, or
+/// `challenge_lockout` seconds before it. `withdraw` only opens strictly after the expiration,
+/// so the two never overlap and a challenge and a withdrawal in the same slot can't race.
+fn require_challenge_window_open(config: &VaultConfig, request: &WithdrawalRequest) -> Result<()> {
+    require!(
+        Clock::get()?.unix_timestamp <= request.expiration - config.challenge_lockout,
+        ClearnetError::ChallengeWindowClosed
+    );
+    Ok(())
+}
+
+/// Emits the events of `request` being rejected by a state at `candidate_height`.
+fn emit_rejection(
+    config: &VaultConfig,
+    request: &WithdrawalRequest,
+    candidate_height: u64,
+) -> Result<()> {
+    emit_lifecycle(
+        config,
+        RequestPhase::Challenged,
+        request.wallet,
+        request.token,
+        request.amount,
+        candidate_height,
+    )?;
+    emit_lifecycle(
+        config,
+        RequestPhase::Rejected,
+        request.wallet,
+        request.token,
+        request.amount,
+        request.height,
+    )?;
+    if config.granular_events {
+        emit!(Rejected {
+            wallet: request.wallet,
+            token: request.token,
+            amount: request.amount,
+            candidate_height,
+        });
+    }
+    Ok(())
+}
+
+/// Emits `RequestLifecycle` for `phase` if the config opted in.
+fn emit_lifecycle(
+    config: &VaultConfig,
+    phase: RequestPhase,
+    wallet: Pubkey,
+    token: Pubkey,
+    amount: u64,
+    height: u64,
+) -> Result<()> {
+    if config.lifecycle_events {
+        emit!(RequestLifecycle {
+            phase: phase as u8,
+            wallet,
+            token,
+            amount,
+            height,
+            ts: Clock::get()?.unix_timestamp,
+        });
+    }
+    Ok(())
+}
+
+/// Requires `token_account` to be `owner`'s associated token account for `mint`, unless the
+/// config allows arbitrary token accounts.
+fn require_canonical_token_account(
+    config: &VaultConfig,
+    token_account: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Result<()> {
+    if !config.allow_non_ata {
+        require_keys_eq!(
+            *token_account,
+            get_associated_token_address(owner, mint),
+            ClearnetError::NotAssociatedTokenAccount
+        );
+    }
+    Ok(())
+}
+
+/// Reads the `VaultState` stored at `info`, failing with `VaultNotCreated` if `create_vault`
+/// hasn't been called for its mint.
+fn load_vault_state(info: &AccountInfo) -> Result<VaultState> {
+    require!(
+        info.owner == &crate::ID && !info.data_is_empty(),
+        ClearnetError::VaultNotCreated
+    );
+    VaultState::try_deserialize(&mut &info.data.borrow()[..])
+}
+
+/// Checks `info` is the `[b"vault", mint]` PDA, rebuilding it from the bump stored at
+/// `create_vault` instead of searching for it.
+fn require_vault_token(info: &AccountInfo, vault_state: &VaultState) -> Result<()> {
+    let expected = Pubkey::create_program_address(
+        &[b"vault", vault_state.mint.as_ref(), &[vault_state.bump]],
+        &crate::ID,
+    )
+    .map_err(|_| ErrorCode::ConstraintSeeds)?;
+    require_keys_eq!(info.key(), expected, ErrorCode::ConstraintSeeds);
+    Ok(())
+}
+
+/// Checks the depositor's token account and the vault both hold `mint`, so a deposit can't
+/// be booked against one mint's `VaultState` while moving another mint's tokens.
+fn require_deposit_mints(
+    user_token: &TokenAccount,
+    vault_token: &AccountInfo,
+    mint: Pubkey,
+) -> Result<()> {
+    let vault = TokenAccount::try_deserialize(&mut &vault_token.data.borrow()[..])?;
+    require!(
+        user_token.mint == mint && vault.mint == mint,
+        ClearnetError::MintMismatch
+    );
+    Ok(())
+}
+
+/// Rejects deposits below `min_deposit`, scaled to `mint`'s decimals.
+fn require_min_deposit(config: &VaultConfig, mint: &Mint, amount: u64) -> Result<()> {
+    require!(
+        amount >= config.min_deposit_for(mint.decimals),
+        ClearnetError::DepositTooSmall
+    );
+    Ok(())
+}
+
+/// Grows a program-owned account created with an older layout to `space` bytes, topping up
+/// its rent from `payer`. The new bytes are zeroed, so appended fields read as zero.
+fn grow_account<'info>(
+    info: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    space: usize,
+) -> Result<()> {
+    require_keys_eq!(
+        *info.owner,
+        crate::ID,
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+    if info.data_len() >= space {
+        return Ok(());
+    }
+
+    let shortfall = Rent::get()?
+        .minimum_balance(space)
+        .saturating_sub(info.lamports());
+    if shortfall > 0 {
+        let cpi_ctx = CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: payer.to_account_info(),
+                to: info.clone(),
+            },
+        );
+        system_program::transfer(cpi_ctx, shortfall)?;
+    }
+    info.resize(space)?;
+    Ok(())
+}
+
+/// Writes `vault_state` back to `info` after `load_vault_state` and changes to it.
+fn save_vault_state(info: &AccountInfo, vault_state: &VaultState) -> Result<()> {
+    vault_state.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+/// Whether the withdrawn mint is listed with `add_instant_token`.
+fn is_instant_token(accounts: &Withdraw) -> bool {
+    !accounts.instant_token.data_is_empty()
+}
+
+/// Emits `InstantTokenWithdrawal` when `request` is paid out before its challenge period is
+/// over, which only an instant token allows.
+fn emit_instant_withdrawal(accounts: &Withdraw, request: &WithdrawalRequest) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    if is_instant_token(accounts) && now <= request.expiration {
+        emit!(InstantTokenWithdrawal {
+            wallet: request.wallet,
+            mint: request.token,
+            amount: request.amount,
+            expiration: request.expiration,
+            ts: now,
+        });
+    }
+    Ok(())
+}
+
+/// Every check `withdraw` makes before moving funds, shared with `dry_run_withdraw` so the
+/// two can't drift apart. Returns the pending request.
+///
+/// `node_accounts` are only read for a newer `finalize`, as the `NodeEntry` accounts of its
+/// participants (see `verify_state_signatures`).
+fn check_withdraw(
+    accounts: &Withdraw,
+    node_accounts: &[AccountInfo],
+    finalize: &State,
+) -> Result<WithdrawalRequest> {
+    assert_operational(&accounts.config, &accounts.vault_state, &accounts.blacklist)?;
+    let req_acct = load_request(&accounts.request_account)?;
+    let clock = Clock::get()?;
+
+    if accounts.relayer.key() != accounts.user.key() {
+        let message = relay_withdraw_message(finalize, &accounts.user_token.key())?;
+        require!(
+            ed25519_signed(&accounts.instructions, &accounts.user.key(), &message),
+            ClearnetError::UnauthorizedRelayer
+        );
+    }
+    // The vault is derived from the requested token, so a different mint can't redirect the payout.
+    require_keys_eq!(
+        accounts.mint.key(),
+        req_acct.token,
+        ClearnetError::MintMismatch
+    );
+    require_canonical_token_account(
+        &accounts.config,
+        &accounts.user_token.key(),
+        &accounts.user.key(),
+        &accounts.mint.key(),
+    )?;
+    // Re-checked in case the limits were tightened while the request was pending.
+    require!(
+        req_acct.amount >= accounts.vault_state.min_withdraw,
+        ClearnetError::WithdrawTooSmall
+    );
+    accounts
+        .vault_state
+        .require_within_single_withdraw_cap(req_acct.amount)?;
+    accounts
+        .vault_state
+        .require_within_daily_withdraw_limit(req_acct.amount, clock.unix_timestamp)?;
+    // Closing the request frees its PDA, so replay protection lives in the tombstone.
+    require!(
+        req_acct.height > accounts.finalized.last_height,
+        ClearnetError::HeightAlreadyFinalized
+    );
+    // Strictly after: at `expiration` itself a challenge still wins (see
+    // `require_challenge_window_open`), whatever the order within the slot.
+    require!(
+        is_instant_token(accounts) || clock.unix_timestamp > req_acct.expiration,
+        ClearnetError::ChallengePeriodNotExpired
+    );
+    // The vault and tombstone accounts are derived from `finalize`.
+    require!(
+        finalize.wallet == req_acct.wallet && finalize.token == req_acct.token,
+        ClearnetError::StateMismatch
+    );
+    // A state newer than the requested one may finalize it too, unless the operator wants
+    // exact heights. It has to be signed like any other and still cover the amount.
+    if finalize.height != req_acct.height {
+        require!(
+            !accounts.config.strict_finalize_height && finalize.height > req_acct.height,
+            ClearnetError::StateMismatch
+        );
+        finalize.validate()?;
+        require!(
+            finalize.balance >= req_acct.amount,
+            ClearnetError::InsufficientStateBalance
+        );
+        accounts
+            .config
+            .require_participants_within_cap(&finalize.participants)?;
+        let (node_accounts, _) = split_participant_accounts(&finalize.participants, node_accounts)?;
+        verify_state_signatures(
+            &accounts.config,
+            &accounts.instructions,
+            node_accounts,
+            finalize,
+        )?;
+    }
+
+    // Would otherwise surface as the token program's own error on transfer.
+    let (fee, _) = withdraw_fee(accounts, req_acct.amount);
+    require!(
+        accounts.vault_token.amount >= req_acct.amount - fee,
+        ClearnetError::VaultInsolvent
+    );
+    Ok(req_acct)
+}
+
+/// The fee `withdraw` charges on `amount`, and whether the user's `FeeExemption` waived it.
+fn withdraw_fee(accounts: &Withdraw, amount: u64) -> (u64, bool) {
+    let fee_exempt = !accounts.fee_exemption.data_is_empty();
+    let fee = if fee_exempt {
+        0
+    } else {
+        compute_fee(
+            amount,
+            accounts.config.fee_bps,
+            accounts.config.fee_round_up,
+        )
+    };
+    (fee, fee_exempt)
+}
+
+/// Fails with `RentExemptViolation` unless `info` still holds the rent-exempt minimum for its
+/// size. Run after taking lamports directly from an account that stays open, since only the
+/// surplus on top of its rent (a bond, bounty or incentive) is ever meant to leave it.
+fn ensure_rent_exempt(info: &AccountInfo, rent: &Rent) -> Result<()> {
+    require!(
+        rent.is_exempt(info.lamports(), info.data_len()),
+        ClearnetError::RentExemptViolation
+    );
+    Ok(())
+}
+
+/// Charges the relayer `sol_fee` lamports into the `[b"sol_fees"]` vault, returning it.
+fn charge_sol_fee(accounts: &mut Withdraw) -> Result<u64> {
+    let sol_fee = accounts.config.sol_fee;
+    if sol_fee > 0 {
+        let cpi_ctx = CpiContext::new(
+            accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: accounts.relayer.to_account_info(),
+                to: accounts.sol_fee_vault.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_ctx, sol_fee)?;
+        accounts.sol_fee_vault.collected += sol_fee;
+    }
+    Ok(sol_fee)
+}
+
+/// Reads the pending request stored at `info`, failing with `NoPendingRequest` if the PDA
+/// was never created, has been closed, or holds no active request.
+fn load_request(info: &AccountInfo) -> Result<WithdrawalRequest> {
+    require!(
+        info.owner == &crate::ID && !info.data_is_empty(),
+        ClearnetError::NoPendingRequest
+    );
+    let request = WithdrawalRequest::try_deserialize(&mut &info.data.borrow()[..])?;
+    require!(request.expiration > 0, ClearnetError::NoPendingRequest);
+    Ok(request)
+}
+
+/// Closes `request` to `user` (refunding its rent and escrowed bounty) and records `height` as
+/// finalized, writing the tombstone back immediately.
+///
+/// Payouts follow checks-effects-interactions: this runs before the transfer CPI, so anything
+/// that re-enters during the transfer finds the request already gone. Anchor would otherwise
+/// only persist account changes after the handler returns.
+///
+/// Vaults only accept classic SPL Token mints (`Program<Token>`, `Account<Mint>`), whose
+/// transfers never call out to other programs; Token-2022 mints with transfer hooks, the one
+/// way a payout could re-enter today, are rejected. The ordering is defence in depth for when
+/// that changes.
+fn consume_request<'info>(
+    request: &AccountInfo<'info>,
+    user: &AccountInfo<'info>,
+    finalized: &mut Account<FinalizedHeight>,
+    height: u64,
+) -> Result<()> {
+    finalized.last_height = height;
+    finalized.exit(&crate::ID)?;
+
+    let lamports = request.lamports();
+    request.sub_lamports(lamports)?;
+    user.add_lamports(lamports)?;
+    request.assign(&system_program::ID);
+    request.resize(0)?;
+    Ok(())
+}
+
+/// Transfers `amount` out of the vault, signed by the vault PDA.
+///
+/// `vault_token` must be its own authority (enforced by `token::authority` on the accounts
+/// structs), since the vault seeds are the only signer.
+fn vault_transfer<'info>(
+    token_program: &Program<'info, Token>,
+    vault_token: &Account<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Transfer {
+        from: vault_token.to_account_info(),
+        to,
+        authority: vault_token.to_account_info(), // The PDA is the owner
+    };
+    let cpi_ctx =
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer);
+    token::transfer(cpi_ctx, amount)
+}
+
+/// Ensures admin instructions carry `config.threshold` distinct signatures from the admin set.
+///
+/// The `admin` signer counts as one approval; co-signers are passed as signing `remaining_accounts`.
+fn require_admin_threshold(
+    config: &VaultConfig,
+    admin: &Signer,
+    cosigners: &[AccountInfo],
+) -> Result<()> {
+    let mut approvals: Vec<Pubkey> = Vec::with_capacity(MAX_ADMINS);
+    let signers = std::iter::once(admin.key())
+        .chain(cosigners.iter().filter(|a| a.is_signer).map(|a| a.key()));
+    for key in signers {
+        if config.is_admin(&key) && !approvals.contains(&key) {
+            approvals.push(key);
+        }
+    }
+    require!(
+        approvals.len() >= config.threshold as usize,
+        ClearnetError::InsufficientAdminSignatures
+    );
+    Ok(())
+}
+
+/// Single gate for the user-facing instructions: the wallet isn't blacklisted, and neither its
+/// token nor the vault as a whole is paused. Fails with the most specific reason.
+///
+/// Challenges don't go through it: a pause must not let a request expire unchallenged, and
+/// nothing is paid out to the wallet until `withdraw` passes the gate again.
+///
+/// `blacklist` is the wallet's `[b"blacklist", wallet]` PDA, checked by the accounts struct;
+/// it holds data only while the wallet is blacklisted.
+fn assert_operational(
+    config: &VaultConfig,
+    vault_state: &VaultState,
+    blacklist: &AccountInfo,
+) -> Result<()> {
+    require!(blacklist.data_is_empty(), ClearnetError::Blacklisted);
+    require!(!vault_state.paused, ClearnetError::TokenPaused);
+    require!(!config.paused, ClearnetError::Paused);
+    Ok(())
+}
+
+/// Requires each participant's signature over `hash_state(state)` to be verified by the
+/// Ed25519Program instruction immediately before the current one.
+///
+/// Clients must pack all of a state's signatures into that single instruction (the Ed25519
+/// program verifies any number of entries natively), so the program introspects it once no
+/// matter how many nodes signed.
+///
+/// Signing participants alone prove nothing, since anyone can list their own keys: the
+/// participants' `NodeEntry` accounts, in `node_accounts` (see `split_participant_accounts`),
+/// must also reach `require_quorum`.
+fn verify_state_signatures(
+    config: &VaultConfig,
+    instructions: &AccountInfo,
+    node_accounts: &[AccountInfo],
+    state: &State,
+) -> Result<()> {
+    verify_signed_message(
+        config,
+        instructions,
+        node_accounts,
+        state,
+        &hash_state(state)?,
+    )
+}
+
+/// `verify_state_signatures` for a new request of `amount` by `recipient`: with
+/// `sign_amounts` on, the signatures must be over `withdrawal_amount_message` instead.
+fn verify_request_signatures(
+    config: &VaultConfig,
+    instructions: &AccountInfo,
+    node_accounts: &[AccountInfo],
+    state: &State,
+    recipient: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let message = if config.sign_amounts {
+        withdrawal_amount_message(state, recipient, amount)?
+    } else {
+        hash_state(state)?
+    };
+    verify_signed_message(config, instructions, node_accounts, state, &message)
+}
+
+/// Shared by `verify_state_signatures` and `verify_request_signatures`.
+fn verify_signed_message(
+    config: &VaultConfig,
+    instructions: &AccountInfo,
+    node_accounts: &[AccountInfo],
+    state: &State,
+    message: &[u8; 32],
+) -> Result<()> {
+    prevalidate_signatures(&state.participants, &state.sigs)?;
+    let ix = preceding_ed25519_instruction(instructions)?;
+    require!(
+        signed_by_all(&ix.data, state, message),
+        ClearnetError::InvalidSignature
+    );
+    require_quorum(instructions, node_accounts, message, config)?;
+    Ok(())
+}
+
+/// The Ed25519Program instruction immediately before the current one.
+fn preceding_ed25519_instruction(instructions: &AccountInfo) -> Result<Instruction> {
+    let current = ix_sysvar::load_current_index_checked(instructions)? as usize;
+    Ok(current
+        .checked_sub(1)
+        .map(|index| ix_sysvar::load_instruction_at_checked(index, instructions))
+        .transpose()?
+        .filter(|ix| ix.program_id == ed25519_program::ID)
+        .ok_or(ClearnetError::MissingEd25519Instruction)?)
+}
+
+/// Whether the Ed25519Program instruction data `ed25519_data` checks every participant's
+/// signature over `hash_state(state)`.
+fn state_signed(ed25519_data: &[u8], state: &State) -> Result<bool> {
+    Ok(signed_by_all(ed25519_data, state, &hash_state(state)?))
+}
+
+/// Whether `ed25519_data` checks every participant's signature in `state` over `message`.
+fn signed_by_all(ed25519_data: &[u8], state: &State, message: &[u8; 32]) -> bool {
+    let entries: Vec<_> = ed25519_entries(ed25519_data).collect();
+    state
+        .participants
+        .iter()
+        .zip(&state.sigs)
+        .all(|(participant, sig)| {
+            entries.iter().any(|&(pubkey, signature, msg)| {
+                pubkey == participant.as_ref() && signature == sig.as_slice() && msg == message
+            })
+        })
+}
+
+/// Cheap shape checks run before any signature verification, so malformed input fails without
+/// spending compute on introspection: one 64-byte signature per participant, and every
+/// participant a valid (on-curve) Ed25519 key.
+fn prevalidate_signatures(participants: &[Pubkey], sigs: &[Vec<u8>]) -> Result<()> {
+    require!(sigs.len() == participants.len(), ClearnetError::SigMismatch);
+    require!(
+        sigs.iter().all(|sig| sig.len() == 64),
+        ClearnetError::BadSignatureLength
+    );
+    require!(
+        participants
+            .iter()
+            .all(|key| validate_edwards(&PodEdwardsPoint(key.to_bytes()))),
+        ClearnetError::InvalidParticipantKey
+    );
+    Ok(())
+}
+
+/// Smallest share of `total` that reaches `QUORUM_BPS`, rounded up.
+fn quorum_of(total: u64) -> u64 {
+    (total as u128 * QUORUM_BPS as u128).div_ceil(10_000) as u64
+}
+
+/// Challenger's share of `bounty` when a same-height state proves `balance` was inflated to
+/// `proven_balance`: `bounty * (balance - proven_balance) / balance`, rounded down so any
+/// remainder is refunded to the user.
+fn reduction_bounty_share(bounty: u64, balance: u64, proven_balance: u64) -> u64 {
+    if balance == 0 {
+        return 0;
+    }
+    // The reduction is at most `balance`, so the result is at most `bounty`.
+    let reduction = balance.saturating_sub(proven_balance) as u128;
+    (bounty as u128 * reduction / balance as u128) as u64
+}
+
+/// Protocol fee on a withdrawal of `amount`. By default it is rounded down, so the user keeps
+/// any sub-unit remainder; `round_up` rounds it up in the fee recipient's favour instead.
+///
+/// Fee rates are capped at `MAX_FEE_BPS`, so the fee never exceeds `amount` and the net payout
+/// `amount - fee` can't underflow.
+fn compute_fee(amount: u64, fee_bps: u16, round_up: bool) -> u64 {
+    // u64 * u16 fits in u128, and the result is at most `amount`.
+    let scaled = amount as u128 * fee_bps as u128;
+    let fee = if round_up {
+        scaled.div_ceil(10_000)
+    } else {
+        scaled / 10_000
+    };
+    fee as u64
+}
+
+/// Fills a freshly created config with `admin` and the defaults every deployment starts from.
+/// `challenge_period` of `0` selects `DEFAULT_CHALLENGE_PERIOD`.
+fn init_config(config: &mut VaultConfig, admin: Pubkey, challenge_period: i64) -> Result<()> {
+    let challenge_period = if challenge_period == 0 {
+        DEFAULT_CHALLENGE_PERIOD
+    } else {
+        challenge_period
+    };
+    validate_challenge_period(challenge_period)?;
+
+    config.admin = admin;
+    config.challenge_period = challenge_period;
+
+    // Single admin by default; `set_admins` can upgrade to a multisig later. Shared with
+    // `migrate_config`, so migrated accounts end up with the same defaults.
+    config.default_fields_since(0);
+    config.version = CONFIG_VERSION;
+
+    let clock = Clock::get()?;
+    config.created_at = clock.unix_timestamp;
+    config.created_slot = clock.slot;
+    emit!(AdminChanged {
+        old: Pubkey::default(),
+        new: config.admin,
+        ts: clock.unix_timestamp,
+    });
+    Ok(())
+}
+
+fn validate_challenge_period(challenge_period: i64) -> Result<()> {
+    require!(challenge_period > 0, ClearnetError::InvalidChallengePeriod);
+    require!(
+        challenge_period >= MIN_CHALLENGE_PERIOD,
+        ClearnetError::ChallengePeriodTooShort
+    );
+    require!(
+        challenge_period <= MAX_CHALLENGE_PERIOD,
+        ClearnetError::InvalidChallengePeriod
+    );
+    Ok(())
+}
+
+// --- Accounts ---
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init, 
+        payer = admin, 
+        space = 8 + VaultConfig::INIT_SPACE,
+        seeds = [b"config"], 
+        bump
+    )]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAdmins<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    /// CHECK: May be shorter than the current `VaultConfig` layout, so it is only deserialized
+    /// (and the admin checked) in the handler once grown.
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(node_authority: Pubkey)]
+pub struct MigrateNode<'info> {
+    /// The node's authority or an admin; pays for the extra rent.
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: May be shorter than the current `NodeEntry` layout, so it is only deserialized
+    /// (and the signer checked) in the handler once grown.
+    #[account(mut, seeds = [b"node", node_authority.as_ref()], bump)]
+    pub node_entry: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVaultState<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", vault_state.mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct BlacklistWallet<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + BlacklistEntry::INIT_SPACE,
+        seeds = [b"blacklist", wallet.as_ref()],
+        bump
+    )]
+    pub blacklist_entry: Account<'info, BlacklistEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(wallet: Pubkey)]
+pub struct AddFeeExemption<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + FeeExemption::INIT_SPACE,
+        seeds = [b"fee_exempt", wallet.as_ref()],
+        bump
+    )]
+    pub fee_exemption: Account<'info, FeeExemption>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveFeeExemption<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"fee_exempt", fee_exemption.wallet.as_ref()],
+        bump
+    )]
+    pub fee_exemption: Account<'info, FeeExemption>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct AddInstantToken<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + InstantToken::INIT_SPACE,
+        seeds = [b"instant_token", mint.as_ref()],
+        bump
+    )]
+    pub instant_token: Account<'info, InstantToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveInstantToken<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"instant_token", instant_token.mint.as_ref()],
+        bump
+    )]
+    pub instant_token: Account<'info, InstantToken>,
+}
+
+#[derive(Accounts)]
+pub struct UnblacklistWallet<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"blacklist", blacklist_entry.wallet.as_ref()],
+        bump
+    )]
+    pub blacklist_entry: Account<'info, BlacklistEntry>,
+}
+
+#[derive(Accounts)]
+pub struct GetConfig<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+}
+
+#[derive(Accounts)]
+pub struct GetLimits<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(seeds = [b"vault_state", vault_state.mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// Read for its decimals, to scale `min_deposit`.
+    #[account(address = vault_state.mint)]
+    pub mint: Account<'info, Mint>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct AssertSolvent<'info> {
+    #[account(seeds = [b"vault_state", mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(seeds = [b"vault", mint.as_ref()], bump = vault_state.bump)]
+    pub vault_token: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct QueueReconcileTotal<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(seeds = [b"vault_state", mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + PendingReconcile::INIT_SPACE,
+        seeds = [b"reconcile", mint.as_ref()],
+        bump
+    )]
+    pub pending: Account<'info, PendingReconcile>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct ReconcileTotal<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(seeds = [b"vault", mint.as_ref()], bump = vault_state.bump)]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(mut, close = admin, seeds = [b"reconcile", mint.as_ref()], bump)]
+    pub pending: Account<'info, PendingReconcile>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct GetHealth<'info> {
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(seeds = [b"vault_state", mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(seeds = [b"vault", mint.as_ref()], bump = vault_state.bump)]
+    pub vault_token: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct GetRequest<'info> {
+    #[account(
+        seeds = [b"request", request_account.wallet.as_ref()],
+        bump = request_account.bump
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+}
+
+#[derive(Accounts)]
+#[instruction(node_authority: Pubkey)]
+pub struct GetNode<'info> {
+    #[account(seeds = [b"node", node_authority.as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+}
+
+#[derive(Accounts)]
+#[instruction(status: bool)]
+pub struct SetNodeStatus<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + NodeEntry::INIT_SPACE,
+        seeds = [b"node", node_authority.key().as_ref()],
+        bump
+    )]
+    pub node_entry: Account<'info, NodeEntry>,
+    /// CHECK: The node's public key
+    pub node_authority: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    /// Required when deactivating a bonded node.
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + ExitingBond::INIT_SPACE,
+        seeds = [b"exiting_bond", node_authority.key().as_ref()],
+        bump
+    )]
+    pub exiting_bond: Option<Account<'info, ExitingBond>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(new_authority: Pubkey)]
+pub struct RotateNodeKey<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"node", node_entry.authority.as_ref()],
+        bump
+    )]
+    pub node_entry: Account<'info, NodeEntry>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + NodeEntry::INIT_SPACE,
+        seeds = [b"node", new_authority.as_ref()],
+        bump
+    )]
+    pub new_node_entry: Account<'info, NodeEntry>,
+    /// CHECK: The old key's bond escrow, which must be empty; read only to check that.
+    #[account(seeds = [b"exiting_bond", node_entry.authority.as_ref()], bump)]
+    pub exiting_bond: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(node_authority: Pubkey)]
+pub struct SetNodeWeight<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"node", node_authority.as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"node", authority.key().as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+}
+
+#[derive(Accounts)]
+pub struct BondNode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"node", authority.key().as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExitNode<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [b"node", authority.key().as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + ExitingBond::INIT_SPACE,
+        seeds = [b"exiting_bond", authority.key().as_ref()],
+        bump
+    )]
+    pub exiting_bond: Account<'info, ExitingBond>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DeactivateStaleNode<'info> {
+    /// Anyone; pays for the exit escrow if the node has none yet.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"node", node_entry.authority.as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ExitingBond::INIT_SPACE,
+        seeds = [b"exiting_bond", node_entry.authority.as_ref()],
+        bump
+    )]
+    pub exiting_bond: Account<'info, ExitingBond>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimNodeBond<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"exiting_bond", authority.key().as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub exiting_bond: Account<'info, ExitingBond>,
+}
+
+#[derive(Accounts)]
+pub struct ReportEquivocation<'info> {
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+    #[account(mut, seeds = [b"node", node_entry.authority.as_ref()], bump)]
+    pub node_entry: Account<'info, NodeEntry>,
+    #[account(mut, seeds = [b"exiting_bond", node_entry.authority.as_ref()], bump)]
+    pub exiting_bond: Option<Account<'info, ExitingBond>>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program checks of the node's signatures.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = reporter,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [b"treasury"],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + VaultState::INIT_SPACE,
+        seeds = [b"vault_state", mint.key().as_ref()],
+        bump
+    )]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"vault", mint.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = vault_token,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterToken<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(seeds = [b"vault_state", vault_state.mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + TokenRegistry::INIT_SPACE,
+        seeds = [b"token_registry"],
+        bump
+    )]
+    pub token_registry: Account<'info, TokenRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnregisterToken<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"token_registry"], bump)]
+    pub token_registry: Account<'info, TokenRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: Loaded in the handler (`load_vault_state`) so a mint without a vault fails with
+    /// `VaultNotCreated`.
+    #[account(mut, seeds = [b"vault_state", mint.key().as_ref()], bump)]
+    pub vault_state: UncheckedAccount<'info>,
+    /// CHECK: Created together with `vault_state` by `create_vault`; checked against the
+    /// stored bump in the handler (`require_vault_token`), and by the token program against
+    /// `user_token`'s mint on transfer.
+    #[account(mut)]
+    pub vault_token: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Blacklist PDA of the user; only inspected for existence.
+    #[account(seeds = [b"blacklist", user.key().as_ref()], bump)]
+    pub blacklist: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct DepositFromDelegate<'info> {
+    pub caller: Signer<'info>,
+    #[account(mut, token::mint = mint)]
+    pub user_token: Account<'info, TokenAccount>,
+    /// CHECK: Signing PDA that token owners approve as the delegate of their token account.
+    #[account(seeds = [b"delegate"], bump)]
+    pub delegate: UncheckedAccount<'info>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: Loaded in the handler (`load_vault_state`) so a mint without a vault fails with
+    /// `VaultNotCreated`.
+    #[account(mut, seeds = [b"vault_state", mint.key().as_ref()], bump)]
+    pub vault_state: UncheckedAccount<'info>,
+    /// CHECK: Created together with `vault_state` by `create_vault`; checked against the
+    /// stored bump in the handler (`require_vault_token`), and by the token program against
+    /// `user_token`'s mint on transfer.
+    #[account(mut)]
+    pub vault_token: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Blacklist PDA of the token owner; only inspected for existence.
+    #[account(seeds = [b"blacklist", user_token.owner.as_ref()], bump)]
+    pub blacklist: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(deposit_ref: [u8; 32])]
+pub struct ConfirmDeposit<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Created on first use; `confirmed_at` tells a stored confirmation from a fresh one.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + DepositConfirmation::INIT_SPACE,
+        seeds = [b"confirmed", deposit_ref.as_ref()],
+        bump
+    )]
+    pub confirmation: Account<'info, DepositConfirmation>,
+    #[account(seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Instructions sysvar, used to find the nodes' Ed25519Program signature checks.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(state: State)]
+pub struct Request<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>, // Wallet owner
+    #[account(
+        init,
+        payer = user,
+        space = WithdrawalRequest::LEN,
+        seeds = [b"request", user.key().as_ref()],
+        bump
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", state.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// CHECK: Blacklist PDA of the user; only inspected for existence.
+    #[account(seeds = [b"blacklist", user.key().as_ref()], bump)]
+    pub blacklist: UncheckedAccount<'info>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program signature check.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositAndRequest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(mut)]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    /// CHECK: Loaded in the handler (`load_vault_state`), as in `Deposit`.
+    #[account(mut, seeds = [b"vault_state", mint.key().as_ref()], bump)]
+    pub vault_state: UncheckedAccount<'info>,
+    /// CHECK: Created together with `vault_state` by `create_vault`, as in `Deposit`.
+    #[account(mut)]
+    pub vault_token: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = user,
+        space = WithdrawalRequest::LEN,
+        seeds = [b"request", user.key().as_ref()],
+        bump
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Blacklist PDA of the user; only inspected for existence.
+    #[account(seeds = [b"blacklist", user.key().as_ref()], bump)]
+    pub blacklist: UncheckedAccount<'info>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program signature check.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddChallengeIncentive<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    /// CHECK: Wallet whose pending request the incentive is for. Only its key is used.
+    pub wallet: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"request", wallet.key().as_ref()],
+        bump = request_account.bump,
+        has_one = wallet
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+    #[account(
+        init_if_needed,
+        payer = funder,
+        space = 8 + ChallengeIncentive::INIT_SPACE,
+        seeds = [b"incentive", wallet.key().as_ref(), funder.key().as_ref()],
+        bump
+    )]
+    pub incentive: Account<'info, ChallengeIncentive>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimChallengeIncentive<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    #[account(
+        mut,
+        close = funder,
+        seeds = [b"incentive", incentive.wallet.as_ref(), funder.key().as_ref()],
+        bump,
+        has_one = funder
+    )]
+    pub incentive: Account<'info, ChallengeIncentive>,
+    /// CHECK: The wallet's request PDA, loaded in the handler to see whether the request the
+    /// incentive was escrowed for is still pending.
+    #[account(seeds = [b"request", incentive.wallet.as_ref()], bump)]
+    pub request_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(candidate: State)]
+pub struct Challenge<'info> {
+    #[account(mut)]
+    pub challenger: Signer<'info>, // Can be node or anyone? Vault.sol said Owner or Node.
+
+    #[account(
+        mut,
+        close = challenger, // Send rent to challenger
+        seeds = [b"request", candidate.wallet.as_ref()],
+        bump = request_account.bump,
+        has_one = wallet
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+
+    // We should verify challenger is a node?
+    // In Vault.sol: `candidate.wallet == msg.sender || isNode[msg.sender]`
+    // Here we can check if `challenger` matches `wallet` OR if a `NodeEntry` exists for `challenger`.
+    // It's cleaner to separate, but for now we assume validation logic inside or flexible.
+    /// CHECK: Wallet being challenged; receives the unearned bounty when a challenge only
+    /// proves a lower balance.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", request_account.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program signature check.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeMulti<'info> {
+    #[account(mut)]
+    pub challenger: Signer<'info>,
+    #[account(
+        mut,
+        close = challenger,
+        seeds = [b"request", wallet.key().as_ref()],
+        bump = request_account.bump,
+        has_one = wallet
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+    /// CHECK: Wallet being challenged; receives the unearned bounty when the winning candidate
+    /// only proves a lower balance.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", request_account.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program signature check.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeAndRequest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"request", user.key().as_ref()],
+        bump = request_account.bump,
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", request_account.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program signature check.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(finalize: State)]
+pub struct Withdraw<'info> {
+    /// The user, or a relayer holding the user's signature (see `withdraw`). Pays for the
+    /// finalized-height tombstone if it doesn't exist yet.
+    #[account(mut)]
+    pub relayer: Signer<'info>,
+    /// CHECK: Owner of the request; receives its rent and bounty back. Only its key is used.
+    #[account(mut)]
+    pub user: UncheckedAccount<'info>,
+
+    /// CHECK: The user's request PDA. Loaded in the handler (`load_request`) so a missing
+    /// request fails with `NoPendingRequest` instead of an account error, and closed there too.
+    #[account(mut, seeds = [b"request", user.key().as_ref()], bump)]
+    pub request_account: UncheckedAccount<'info>,
+    // Derived from `finalize`, which the handler checks against the stored request.
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + FinalizedHeight::INIT_SPACE,
+        seeds = [b"finalized", finalize.wallet.as_ref(), finalize.token.as_ref()],
+        bump
+    )]
+    pub finalized: Account<'info, FinalizedHeight>,
+
+    #[account(mut)]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"vault_state", finalize.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// Self-owned: the vault PDA is both the token account and its authority, which is what
+    /// lets `vault_transfer` sign with the vault seeds alone.
+    #[account(
+        mut,
+        seeds = [b"vault", finalize.token.as_ref()],
+        bump = vault_state.bump,
+        token::authority = vault_token,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Blacklist PDA of the user; only inspected for existence.
+    #[account(seeds = [b"blacklist", user.key().as_ref()], bump)]
+    pub blacklist: UncheckedAccount<'info>,
+    /// CHECK: Fee exemption PDA of the user; only inspected for existence.
+    #[account(seeds = [b"fee_exempt", user.key().as_ref()], bump)]
+    pub fee_exemption: UncheckedAccount<'info>,
+    /// CHECK: Instant token PDA of the mint; only inspected for existence.
+    #[account(seeds = [b"instant_token", mint.key().as_ref()], bump)]
+    pub instant_token: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, used to find the user's signature when relayed.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    /// Collects `sol_fee`; created by the first withdrawal that needs it.
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        space = 8 + SolFeeVault::INIT_SPACE,
+        seeds = [b"sol_fees"],
+        bump
+    )]
+    pub sol_fee_vault: Account<'info, SolFeeVault>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(finalize: State)]
+pub struct InstantWithdraw<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: The user's request PDA. Loaded in the handler (`load_request`) so a missing
+    /// request fails with `NoPendingRequest` instead of an account error, and closed there too.
+    #[account(mut, seeds = [b"request", user.key().as_ref()], bump)]
+    pub request_account: UncheckedAccount<'info>,
+    // Derived from `finalize`, which the handler checks against the stored request.
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + FinalizedHeight::INIT_SPACE,
+        seeds = [b"finalized", finalize.wallet.as_ref(), finalize.token.as_ref()],
+        bump
+    )]
+    pub finalized: Account<'info, FinalizedHeight>,
+
+    #[account(mut)]
+    pub user_token: Account<'info, TokenAccount>,
+    pub mint: Account<'info, Mint>,
+    #[account(mut, seeds = [b"vault_state", finalize.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// Self-owned: the vault PDA is both the token account and its authority, which is what
+    /// lets `vault_transfer` sign with the vault seeds alone.
+    #[account(
+        mut,
+        seeds = [b"vault", finalize.token.as_ref()],
+        bump = vault_state.bump,
+        token::authority = vault_token,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    /// CHECK: Blacklist PDA of the user; only inspected for existence.
+    #[account(seeds = [b"blacklist", user.key().as_ref()], bump)]
+    pub blacklist: UncheckedAccount<'info>,
+    /// CHECK: Fee exemption PDA of the user; only inspected for existence.
+    #[account(seeds = [b"fee_exempt", user.key().as_ref()], bump)]
+    pub fee_exemption: UncheckedAccount<'info>,
+    /// CHECK: Instructions sysvar, used to find the Ed25519Program signature checks.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepExpiredRequest<'info> {
+    #[account(mut)]
+    pub sweeper: Signer<'info>,
+    /// The sweeper's node entry; only needed while sweeping is restricted to nodes.
+    #[account(seeds = [b"node", sweeper.key().as_ref()], bump)]
+    pub node_entry: Option<Account<'info, NodeEntry>>,
+    #[account(
+        mut,
+        close = wallet,
+        seeds = [b"request", wallet.key().as_ref()],
+        bump = request_account.bump,
+        has_one = wallet
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+    /// CHECK: Owner of the request; receives its rent and bounty.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", request_account.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeRequest<'info> {
+    pub submitter: Signer<'info>,
+    #[account(
+        mut,
+        close = wallet,
+        seeds = [b"request", wallet.key().as_ref()],
+        bump = request_account.bump,
+        has_one = wallet
+    )]
+    pub request_account: Account<'info, WithdrawalRequest>,
+    /// CHECK: Owner of the request and signer of the revocation; receives its rent and bounty.
+    #[account(mut)]
+    pub wallet: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"config"], bump)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", request_account.token.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    /// CHECK: Instructions sysvar, used to find the revocation's Ed25519Program signature check.
+    #[account(address = ix_sysvar::ID)]
+    pub instructions: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimFees<'info> {
+    pub fee_recipient: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = fee_recipient)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(mut, seeds = [b"vault_state", vault_state.mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", vault_state.mint.as_ref()],
+        bump = vault_state.bump,
+        token::authority = vault_token,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = fee_token.mint == vault_state.mint @ ClearnetError::InvalidFeeAccount,
+    )]
+    pub fee_token: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateVault<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump, has_one = admin)]
+    pub config: Account<'info, VaultConfig>,
+    #[account(seeds = [b"vault_state", vault_state.mint.as_ref()], bump)]
+    pub vault_state: Account<'info, VaultState>,
+    #[account(
+        mut,
+        seeds = [b"vault", vault_state.mint.as_ref()],
+        bump = vault_state.bump,
+        token::authority = vault_token,
+    )]
+    pub vault_token: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = new_vault.mint == vault_state.mint @ ClearnetError::MintMismatch,
+        constraint = new_vault.key() != vault_token.key() @ ClearnetError::InvalidVaultMigration,
+    )]
+    pub new_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// --- Data Structures ---
+
+#[account]
+#[derive(InitSpace)]
+pub struct VaultConfig {
+    pub admin: Pubkey,
+    pub challenge_period: i64,
+    /// Admin multisig set; unused slots are `Pubkey::default()`.
+    pub admins: [Pubkey; MAX_ADMINS],
+    /// Number of distinct admin signatures required for admin instructions.
+    pub threshold: u8,
+    /// Withdrawal fee in basis points of the withdrawn amount.
+    pub fee_bps: u16,
+    /// Owner of the token accounts that receive withdrawal fees.
+    pub fee_recipient: Pubkey,
+    /// Seconds a deactivated node's bond remains slashable before it can be withdrawn.
+    pub dispute_window: i64,
+    /// Fee charged by `instant_withdraw`; `0` disables instant withdrawals.
+    pub instant_fee_bps: u16,
+    /// Number of registered nodes currently active.
+    pub active_node_count: u32,
+    /// Sum of the weights of the active nodes.
+    pub active_node_weight: u64,
+    /// Emit redundant events (e.g. `Requested` next to `Challenged`); off trims log usage.
+    pub verbose_events: bool,
+    /// Global pause of deposits, requests, challenges and withdrawals.
+    pub paused: bool,
+    /// Withdrawal requests currently open across all wallets.
+    pub open_request_count: u32,
+    /// Cap on `open_request_count`; `0` means no cap.
+    pub max_open_requests: u32,
+    /// Lamports a user escrows with each request, paid to a successful challenger.
+    pub challenge_bounty: u64,
+    /// Seconds without a heartbeat after which a node stops counting towards quorum; `0`
+    /// disables liveness checks.
+    pub heartbeat_window: i64,
+    /// Whether anyone may sweep expired requests, rather than only active nodes.
+    pub sweep_permissionless: bool,
+    /// Seconds after its challenge period before an unclaimed request may be swept.
+    pub sweep_delay: i64,
+    /// Whether admin keys may also be registered as nodes.
+    pub admin_can_be_node: bool,
+    /// When `initialize` ran. Together with the `AdminChanged` events this gives auditors the
+    /// vault's admin timeline.
+    pub created_at: i64,
+    pub created_slot: u64,
+    /// Whether user token accounts other than the canonical ATA are accepted.
+    pub allow_non_ata: bool,
+    /// Emit `RequestLifecycle` at every step of a request. Off by default.
+    pub lifecycle_events: bool,
+    /// Emit the per-step request events; off leaves `RequestLifecycle` as the only record.
+    pub granular_events: bool,
+    /// Seconds after deactivation before a node can be reactivated; `0` disables the cooldown.
+    pub reactivation_cooldown: i64,
+    /// Round fees up instead of down. Off by default, so the user keeps sub-unit remainders.
+    pub fee_round_up: bool,
+    /// Most participants a state may carry, at most `MAX_PARTICIPANTS`.
+    pub max_participants: u8,
+    /// Layout version of this account (`CONFIG_VERSION`); `0` before `migrate_config` ran.
+    pub version: u8,
+    /// Flat lamport fee per `withdraw`; `0` disables it.
+    pub sol_fee: u64,
+    /// Smallest deposit that emits `Deposited`; `0` emits it for all.
+    pub min_event_amount: u64,
+    /// Only accept the exact requested state in `withdraw`, not a newer one.
+    pub strict_finalize_height: bool,
+    /// Longer challenge periods for large requests, by ascending threshold; unused slots have
+    /// a `0` period.
+    pub challenge_tiers: [ChallengeTier; MAX_CHALLENGE_TIERS],
+    /// Fewest distinct nodes that must sign a quorum attestation, whatever their weight.
+    pub min_signers: u8,
+    /// Seconds a queued `fee_recipient` change waits before it can be executed.
+    pub timelock_delay: i64,
+    /// Fee recipient queued by `queue_fee_recipient`.
+    pub pending_fee_recipient: Pubkey,
+    /// When the queued fee recipient can be executed; `0` when nothing is queued.
+    pub fee_recipient_eta: i64,
+    /// Share of slashed bonds, in basis points, sent to the treasury; the reporter gets the
+    /// rest.
+    pub slash_split_bps: u16,
+    /// Lamports a node must have bonded for its signature to count toward quorum.
+    pub node_bond: u64,
+    /// Seconds before a request's expiration after which it can no longer be challenged.
+    pub challenge_lockout: i64,
+    /// Smallest deposit, in hundredths of a whole token (see `min_deposit_for`).
+    pub min_deposit: u64,
+    /// Lamports `sweep_expired_request` pays the sweeper out of the swept request.
+    pub sweep_reward: u64,
+    /// Requests need signatures over `withdrawal_amount_message` instead of the bare state.
+    pub sign_amounts: bool,
+    /// Most of the active weight, in basis points, a single node may hold; `0` for no cap.
+    pub max_single_weight_bps: u16,
+    /// Furthest, in seconds from now, a state's `deadline` may be; `0` for no cap.
+    pub max_state_lifetime: i64,
+    /// Seconds after activation before a node's signatures count toward quorum.
+    pub activation_grace: i64,
+    /// Emit `DepositAccounted` for every deposit. Off by default.
+    pub deposit_accounting_events: bool,
+}
+
+impl VaultConfig {
+    pub fn is_admin(&self, key: &Pubkey) -> bool {
+        *key != Pubkey::default() && self.admins.contains(key)
+    }
+
+    /// Gives the fields added after layout `from_version` the values `init_config` would have,
+    /// where reading them as zero would change behaviour. Everything before `version` came
+    /// unversioned (`0`); of those, the admin set, fee recipient and participant cap are only
+    /// filled in while still unset, as zero isn't a value an admin could have chosen.
+    pub fn default_fields_since(&mut self, from_version: u8) {
+        if from_version < 1 {
+            if self.threshold == 0 {
+                self.admins = [Pubkey::default(); MAX_ADMINS];
+                self.admins[0] = self.admin;
+                self.threshold = 1;
+            }
+            if self.fee_recipient == Pubkey::default() {
+                self.fee_recipient = self.admin;
+            }
+            if self.max_participants == 0 {
+                self.max_participants = MAX_PARTICIPANTS;
+            }
+            self.dispute_window = DEFAULT_DISPUTE_WINDOW;
+            self.verbose_events = true;
+            self.granular_events = true;
+            self.sweep_permissionless = true;
+            self.sweep_delay = DEFAULT_SWEEP_DELAY;
+        }
+        if from_version < 7 {
+            self.timelock_delay = DEFAULT_TIMELOCK_DELAY;
+        }
+    }
+
+    /// Rejects states with more participants than `max_participants` (or `MAX_PARTICIPANTS`
+    /// for configs created before the setting existed).
+    pub fn require_participants_within_cap(&self, participants: &[Pubkey]) -> Result<()> {
+        require!(
+            participants.len() <= self.participant_cap() as usize,
+            ClearnetError::TooManyParticipants
+        );
+        Ok(())
+    }
+
+    /// `max_participants`, or `MAX_PARTICIPANTS` while it's unset.
+    pub fn participant_cap(&self) -> u8 {
+        match self.max_participants {
+            0 => MAX_PARTICIPANTS,
+            max => max,
+        }
+    }
+
+    /// Rejects a state whose `deadline` has passed, and, with `max_state_lifetime` set, one
+    /// whose deadline is missing or further than that past `now`, so no signature set stays
+    /// usable for longer than the cap.
+    pub fn require_deadline_within_lifetime(&self, state: &State, now: i64) -> Result<()> {
+        require!(
+            state.deadline == 0 || state.deadline >= now,
+            ClearnetError::StateExpired
+        );
+        require!(
+            self.max_state_lifetime == 0
+                || (state.deadline != 0
+                    && state.deadline <= now.saturating_add(self.max_state_lifetime)),
+            ClearnetError::DeadlineTooFar
+        );
+        Ok(())
+    }
+
+    /// Rejects registering an admin as a node unless `admin_can_be_node` is set.
+    pub fn require_node_allowed(&self, authority: &Pubkey) -> Result<()> {
+        require!(
+            self.admin_can_be_node || !self.is_admin(authority),
+            ClearnetError::AdminCannotBeNode
+        );
+        Ok(())
+    }
+
+    /// Checks a node of `weight` joining active nodes that weigh `others` in total: the sum must
+    /// fit in a `u64`, and the node must stay within `max_single_weight_bps` of it.
+    pub fn require_weight_within_limits(&self, others: u64, weight: u64) -> Result<()> {
+        let total = others
+            .checked_add(weight)
+            .ok_or(ClearnetError::WeightOverflow)?;
+        require!(
+            self.max_single_weight_bps == 0
+                || weight as u128 * 10_000 <= total as u128 * self.max_single_weight_bps as u128,
+            ClearnetError::WeightTooConcentrated
+        );
+        Ok(())
+    }
+
+    /// Keeps the active node totals in step with a node going from `was_active` to `is_active`.
+    pub fn track_node(&mut self, weight: u64, was_active: bool, is_active: bool) {
+        match (was_active, is_active) {
+            (false, true) => {
+                self.active_node_count += 1;
+                self.active_node_weight += weight;
+            }
+            (true, false) => {
+                self.active_node_count = self.active_node_count.saturating_sub(1);
+                self.active_node_weight = self.active_node_weight.saturating_sub(weight);
+            }
+            _ => {}
+        }
+    }
+
+    /// `min_deposit` in base units of a mint with `decimals`, rounded up so tokens with fewer
+    /// than `MIN_DEPOSIT_DECIMALS` decimals still get a non-zero minimum.
+    pub fn min_deposit_for(&self, decimals: u8) -> u64 {
+        if self.min_deposit == 0 {
+            return 0;
+        }
+        10u128
+            .checked_pow(decimals as u32)
+            .and_then(|unit| unit.checked_mul(self.min_deposit as u128))
+            .map(|raw| raw.div_ceil(10u128.pow(MIN_DEPOSIT_DECIMALS)))
+            .and_then(|raw| u64::try_from(raw).ok())
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Counts a newly opened request, enforcing `max_open_requests`.
+    pub fn open_request(&mut self) -> Result<()> {
+        require!(
+            self.max_open_requests == 0 || self.open_request_count < self.max_open_requests,
+            ClearnetError::TooManyOpenRequests
+        );
+        self.open_request_count += 1;
+        Ok(())
+    }
+
+    /// Releases the slot of a request that was challenged or paid out.
+    pub fn close_request(&mut self) {
+        self.open_request_count = self.open_request_count.saturating_sub(1);
+    }
+}
+
+/// Read-only snapshot of `VaultConfig` returned by `get_config`.
+///
+/// Fields are only ever appended (with a `version` bump), so clients can keep decoding
+/// the prefix they know about as the config grows.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ConfigView {
+    pub version: u8,
+    pub admin: Pubkey,
+    pub challenge_period: i64,
+    pub admins: [Pubkey; MAX_ADMINS],
+    pub threshold: u8,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub dispute_window: i64,
+    pub instant_fee_bps: u16,
+    pub verbose_events: bool,
+    pub paused: bool,
+    pub open_request_count: u32,
+    pub max_open_requests: u32,
+    pub challenge_bounty: u64,
+    pub heartbeat_window: i64,
+    pub sweep_permissionless: bool,
+    pub sweep_delay: i64,
+    pub admin_can_be_node: bool,
+    pub created_at: i64,
+    pub created_slot: u64,
+    pub allow_non_ata: bool,
+    pub lifecycle_events: bool,
+    pub granular_events: bool,
+    pub reactivation_cooldown: i64,
+    pub fee_round_up: bool,
+    pub max_participants: u8,
+    pub config_version: u8,
+    pub sol_fee: u64,
+    pub min_event_amount: u64,
+    pub strict_finalize_height: bool,
+    pub challenge_tiers: [ChallengeTier; MAX_CHALLENGE_TIERS],
+    pub min_signers: u8,
+    pub timelock_delay: i64,
+    pub pending_fee_recipient: Pubkey,
+    pub fee_recipient_eta: i64,
+    pub slash_split_bps: u16,
+    pub node_bond: u64,
+    pub challenge_lockout: i64,
+    pub min_deposit: u64,
+    pub sweep_reward: u64,
+    pub sign_amounts: bool,
+    pub max_single_weight_bps: u16,
+    pub max_state_lifetime: i64,
+    pub activation_grace: i64,
+    pub deposit_accounting_events: bool,
+}
+
+/// Result of `quote_withdraw`: what the user receives and what goes to the fee recipient.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WithdrawQuote {
+    pub net: u64,
+    pub fee: u64,
+}
+
+/// Result of `get_limits`. Append-only: new fields go at the end and bump `LIMITS_VERSION`.
+/// Configured limits use `0` for "none"; remaining amounts are `MAX` when uncapped.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Limits {
+    pub version: u8,
+    pub min_deposit: u64,
+    /// Largest single deposit (`VaultState::max_deposit`); `0` for no cap.
+    pub max_deposit: u64,
+    /// How much more can be deposited before the vault's deposit cap is reached.
+    pub deposit_cap_headroom: u64,
+    /// Most that can be withdrawn per UTC day (`VaultState::daily_withdraw_limit`); `0` for no
+    /// limit.
+    pub withdraw_rate_limit: u64,
+    /// What can still be withdrawn today.
+    pub daily_cap_remaining: u64,
+    /// Effective challenge period in seconds (the token's override, else the global one).
+    pub challenge_period: i64,
+    pub min_withdraw: u64,
+    /// Requests that can still be opened before `max_open_requests` is reached.
+    pub open_request_headroom: u32,
+    pub max_single_withdraw: u64,
+}
+
+/// Result of `get_node`. Append-only: new fields go at the end and bump `NODE_VIEW_VERSION`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NodeView {
+    pub version: u8,
+    pub authority: Pubkey,
+    pub is_active: bool,
+    pub weight: u64,
+    pub last_seen: i64,
+    pub bond: u64,
+    pub deactivated_at: i64,
+    pub entry_version: u8,
+    pub activated_at: i64,
+}
+
+/// Result of `request`: when the request becomes withdrawable and the challenge period it got.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RequestReceipt {
+    pub expiration: i64,
+    pub height: u64,
+    pub challenge_period: i64,
+}
+
+/// Result of `dry_run_withdraw`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct WithdrawDryRun {
+    /// Code `withdraw` would fail with (a `ClearnetError` from 6000 up, or a runtime error);
+    /// `0` if it would succeed.
+    pub error_code: u64,
+}
+
+/// Result of `deposit`: the mint's vault token account (`[b"vault", mint]`) and its bump.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct VaultAddress {
+    pub vault: Pubkey,
+    pub bump: u8,
+}
+
+/// Result of `get_quorum_requirement`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct QuorumRequirement {
+    pub active_node_count: u32,
+    /// Signatures needed from distinct active nodes.
+    pub quorum: u32,
+    /// Combined weight the signing nodes need.
+    pub weight_quorum: u64,
+    /// Distinct signers needed whatever their weight.
+    pub min_signers: u8,
+}
+
+/// Result of `health`. Append-only: new fields go at the end and bump `HEALTH_VERSION`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Health {
+    pub version: u8,
+    /// Whether the program or this mint is paused.
+    pub paused: bool,
+    pub active_node_count: u32,
+    /// Signatures needed from distinct active nodes.
+    pub quorum: u32,
+    pub open_request_count: u32,
+    pub total_deposited: u64,
+    /// Tokens actually held by the vault token account.
+    pub vault_balance: u64,
+}
+
+/// Result of `get_request`. Append-only: new fields go at the end and bump
+/// `REQUEST_VIEW_VERSION`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RequestView {
+    pub version: u8,
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+    pub amount: u64,
+    pub height: u64,
+    pub expiration: i64,
+    pub balance: u64,
+    pub bounty: u64,
+    pub da_hash: [u8; 32],
+}
+
+/// Result of `get_node_liveness`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct NodeLiveness {
+    /// Active nodes that sent a heartbeat within the window.
+    pub live_count: u32,
+    /// Active nodes that didn't.
+    pub stale: Vec<Pubkey>,
+}
+
+/// Per-mint vault bookkeeping, created alongside the vault token account by `create_vault`.
+#[account]
+#[derive(InitSpace)]
+pub struct VaultState {
+    pub mint: Pubkey,
+    /// Canonical bump of the `[b"vault", mint]` token account PDA.
+    pub bump: u8,
+    /// Per-token challenge period override; `0` falls back to `VaultConfig::challenge_period`.
+    pub challenge_period: i64,
+    /// Pauses this token only; see `VaultConfig::paused` for the global switch.
+    pub paused: bool,
+    /// Smallest withdrawable amount, in the token's base units (so it scales with decimals).
+    pub min_withdraw: u64,
+    /// Withdrawal fees held in the vault until the fee recipient calls `claim_fees`.
+    pub accrued_fees: u64,
+    /// Deposited tokens not yet paid out, i.e. what the vault owes its users.
+    pub total_deposited: u64,
+    /// Sum of the amounts of open withdrawal requests.
+    pub pending_withdrawals: u64,
+    /// Largest amount a single request may withdraw, in base units; `0` means no cap.
+    pub max_single_withdraw: u64,
+    /// Largest single deposit, in base units; `0` means no cap.
+    pub max_deposit: u64,
+    /// Most `total_deposited` may reach through deposits; `0` means no cap.
+    pub deposit_cap: u64,
+    /// Most that may be paid out per UTC day, in base units; `0` means no limit.
+    pub daily_withdraw_limit: u64,
+    /// Start of the day `withdrawn_today` covers.
+    pub withdraw_day: i64,
+    /// Paid out since `withdraw_day` began.
+    pub withdrawn_today: u64,
+}
+
+impl VaultState {
+    pub fn require_within_single_withdraw_cap(&self, amount: u64) -> Result<()> {
+        require!(
+            self.max_single_withdraw == 0 || amount <= self.max_single_withdraw,
+            ClearnetError::WithdrawTooLarge
+        );
+        Ok(())
+    }
+
+    /// Rejects a deposit above `max_deposit` or one that would take `total_deposited` past
+    /// `deposit_cap`.
+    pub fn require_deposit_within_limits(&self, amount: u64) -> Result<()> {
+        require!(
+            self.max_deposit == 0 || amount <= self.max_deposit,
+            ClearnetError::DepositTooLarge
+        );
+        require!(
+            amount <= self.deposit_cap_headroom(),
+            ClearnetError::DepositCapExceeded
+        );
+        Ok(())
+    }
+
+    /// How much more can be deposited before `deposit_cap` is reached; `u64::MAX` without one.
+    pub fn deposit_cap_headroom(&self) -> u64 {
+        match self.deposit_cap {
+            0 => u64::MAX,
+            cap => cap.saturating_sub(self.total_deposited),
+        }
+    }
+
+    /// What can still be paid out on the UTC day containing `now`; `u64::MAX` without a limit.
+    pub fn daily_withdraw_remaining(&self, now: i64) -> u64 {
+        match self.daily_withdraw_limit {
+            0 => u64::MAX,
+            limit => limit.saturating_sub(self.withdrawn_on(now)),
+        }
+    }
+
+    pub fn require_within_daily_withdraw_limit(&self, amount: u64, now: i64) -> Result<()> {
+        require!(
+            amount <= self.daily_withdraw_remaining(now),
+            ClearnetError::DailyWithdrawLimitExceeded
+        );
+        Ok(())
+    }
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+    /// `withdrawn_today`, or `0` once the day it covers is over.
+    fn withdrawn_on(&self, now: i64) -> u64 {
+        if now - now.rem_euclid(WITHDRAW_DAY) == self.withdraw_day {
+            self.withdrawn_today
+        } else {
+            0
+        }
+    }
+
+    /// Books a payout of `amount` made at `now`: it leaves both the deposits owed and the
+    /// pending claims, and counts towards the day's `daily_withdraw_limit`. Saturating, since
+    /// totals only cover deposits made since they were tracked.
+    pub fn settle_withdrawal(&mut self, amount: u64, now: i64) {
+        self.total_deposited = self.total_deposited.saturating_sub(amount);
+        self.pending_withdrawals = self.pending_withdrawals.saturating_sub(amount);
+        self.withdrawn_today = self.withdrawn_on(now).saturating_add(amount);
+        self.withdraw_day = now - now.rem_euclid(WITHDRAW_DAY);
+    }
+
+    /// What the vault must hold: every outstanding deposit, or the open claims if they exceed
+    /// them, plus fees not yet claimed.
+    pub fn liabilities(&self) -> u128 {
+        self.total_deposited.max(self.pending_withdrawals) as u128 + self.accrued_fees as u128
+    }
+
+    pub fn challenge_period(&self, config: &VaultConfig) -> i64 {
+        if self.challenge_period > 0 {
+            self.challenge_period
+        } else {
+            config.challenge_period
+        }
+    }
+
+    /// Challenge period for a request of `amount`: that of the largest tier threshold it
+    /// meets, else the token's (or global) base period. A tier only ever lengthens the base
+    /// period, so a large withdrawal never gets less time to be challenged than a small one.
+    pub fn challenge_period_for(&self, config: &VaultConfig, amount: u64) -> i64 {
+        let base = self.challenge_period(config);
+        config
+            .challenge_tiers
+            .iter()
+            .filter(|tier| tier.period > 0 && amount >= tier.threshold)
+            .max_by_key(|tier| tier.threshold)
+            .map_or(base, |tier| tier.period.max(base))
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(state: State)]
-pub struct Request<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>, // Wallet owner
-    #[account(
-        init,
-        payer = user,
-        space = 8 + 32 + 32 + 8 + 8 + 8 + 1, // Space for Request
-        seeds = [b"request", user.key().as_ref()],
-        bump
-    )]
-    pub request_account: Account<'info, WithdrawalRequest>,
+/// One payout of `withdraw_split`: `amount` (before its fee) to the token account `recipient`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SplitPortion {
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
 
-    #[account(seeds = [b"config"], bump)]
-    pub config: Account<'info, VaultConfig>,
+/// Settings `initialize_and_configure` applies at deployment. `challenge_period` of `0`
+/// selects `DEFAULT_CHALLENGE_PERIOD`, as in `initialize`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitParams {
+    pub challenge_period: i64,
+    pub fee_bps: u16,
+    pub fee_recipient: Pubkey,
+    pub min_signers: u8,
+    pub max_participants: u8,
+    pub max_open_requests: u32,
+    /// In hundredths of a whole token, as for `set_min_deposit`.
+    pub min_deposit: u64,
+}
 
-    pub system_program: Program<'info, System>,
+/// Challenge period applied to requests of at least `threshold` base units.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct ChallengeTier {
+    pub threshold: u64,
+    pub period: i64,
 }
 
-#[derive(Accounts)]
-#[instruction(candidate: State)]
-pub struct Challenge<'info> {
-    #[account(mut)]
-    pub challenger: Signer<'info>, // Can be node or anyone? Vault.sol said Owner or Node.
+/// Holds the lamport fees charged by `withdraw` on top of its rent.
+#[account]
+#[derive(InitSpace)]
+pub struct SolFeeVault {
+    /// Lamports collected over the vault's lifetime.
+    pub collected: u64,
+}
 
-    #[account(
-        mut,
-        close = challenger, // Send rent to challenger
-        seeds = [b"request", candidate.wallet.as_ref()],
-        bump = request_account.bump,
-        has_one = wallet
-    )]
-    pub request_account: Account<'info, WithdrawalRequest>,
+/// Marks `wallet` as blacklisted for as long as the account exists.
+#[account]
+#[derive(InitSpace)]
+pub struct BlacklistEntry {
+    pub wallet: Pubkey,
+}
 
-    // We should verify challenger is a node?
-    // In Vault.sol: `candidate.wallet == msg.sender || isNode[msg.sender]`
-    // Here we can check if `challenger` matches `wallet` OR if a `NodeEntry` exists for `challenger`.
-    // It's cleaner to separate, but for now we assume validation logic inside or flexible.
-    /// CHECK: Wallet being challenged.
-    pub wallet: UncheckedAccount<'info>,
+/// Exempts `wallet` from withdrawal fees for as long as the account exists.
+#[account]
+#[derive(InitSpace)]
+pub struct FeeExemption {
+    pub wallet: Pubkey,
 }
 
-#[derive(Accounts)]
-#[instruction(finalize: State)]
-pub struct Withdraw<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+/// Lets `mint`'s requests skip the challenge period for as long as the account exists (see
+/// `add_instant_token`).
+#[account]
+#[derive(InitSpace)]
+pub struct InstantToken {
+    pub mint: Pubkey,
+}
 
-    #[account(
-        mut,
-        close = user,
-        seeds = [b"request", user.key().as_ref()],
-        bump = request_account.bump
-    )]
-    pub request_account: Account<'info, WithdrawalRequest>,
+#[account]
+#[derive(InitSpace)]
+pub struct NodeEntry {
+    pub authority: Pubkey,
+    pub is_active: bool,
+    /// Bonded lamports, held by this account on top of its rent.
+    pub bond: u64,
+    /// Voting weight towards `weight_quorum`; `1` for every node at registration.
+    pub weight: u64,
+    /// Time of the last `heartbeat` (or activation).
+    pub last_seen: i64,
+    /// When the node was last deactivated, by the admin, `exit_node` or a slash.
+    pub deactivated_at: i64,
+    /// Layout version of this entry (`NODE_ENTRY_VERSION`); `0` before `migrate_node` ran.
+    pub version: u8,
+    /// When the node last joined the active set; its signatures only count toward quorum
+    /// `activation_grace` seconds later. `0` for entries activated before it was recorded.
+    pub activated_at: i64,
+}
 
-    #[account(mut)]
-    pub user_token: Account<'info, TokenAccount>,
-    pub mint: Account<'info, Mint>,
-    #[account(
-        mut,
-        seeds = [b"vault", mint.key().as_ref()],
-        bump,
-    )]
-    pub vault_token: Account<'info, TokenAccount>,
+impl NodeEntry {
+    /// Whether the node has been seen within the last `window` seconds.
+    pub fn is_live(&self, window: i64, now: i64) -> bool {
+        now - self.last_seen <= window
+    }
+}
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+/// Mints the admins listed with `register_token`, in listing order (`[b"token_registry"]`), so
+/// clients can list the supported vaults in one fetch instead of scanning for `VaultState`
+/// accounts.
+#[account]
+#[derive(InitSpace)]
+pub struct TokenRegistry {
+    #[max_len(MAX_REGISTERED_TOKENS)]
+    pub mints: Vec<Pubkey>,
 }
 
-// --- Data Structures ---
+/// Insurance fund (`[b"treasury"]`) holding the treasury's share of slashed bonds on top of
+/// its rent.
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    /// Lamports received from slashing so far.
+    pub slashed: u64,
+}
 
+/// `total_deposited` queued for a mint by `queue_reconcile_total`, applicable from `eta`.
 #[account]
-pub struct VaultConfig {
-    pub admin: Pubkey,
-    pub challenge_period: i64,
+#[derive(InitSpace)]
+pub struct PendingReconcile {
+    pub new_total: u64,
+    pub eta: i64,
 }
 
+/// Bond of a deactivated node, kept slashable until `unlock_ts`.
 #[account]
-pub struct NodeEntry {
+#[derive(InitSpace)]
+pub struct ExitingBond {
     pub authority: Pubkey,
-    pub is_active: bool,
+    /// Escrowed lamports, held by this account on top of its rent.
+    pub amount: u64,
+    pub unlock_ts: i64,
 }
 
+/// A pending withdrawal at `["request", wallet]`.
+///
+/// After the 8-byte discriminator the fields are Borsh-encoded in declaration order: `wallet`,
+/// `token`, `amount`, `height`, `expiration`, `bump`, `balance`, `bounty`, `da_hash`, for
+/// `WithdrawalRequest::LEN` bytes in all. New fields are only appended; clients that would
+/// rather not track the layout can simulate `get_request` instead.
 #[account]
+#[derive(InitSpace)]
 pub struct WithdrawalRequest {
     pub wallet: Pubkey,
     pub token: Pubkey,
@@ -353,20 +5580,279 @@ pub struct WithdrawalRequest {
     pub height: u64,
     pub expiration: i64,
     pub bump: u8,
+    /// Balance of the requested state, used to prove same-height fraud.
+    pub balance: u64,
+    /// Lamports escrowed on top of rent for whoever successfully challenges the request.
+    pub bounty: u64,
+    /// `State::da_hash` of the requested state, used to prove a same-height fork.
+    pub da_hash: [u8; 32],
+}
+
+impl WithdrawalRequest {
+    /// Account size, discriminator included.
+    pub const LEN: usize = 8 + Self::INIT_SPACE;
+}
+
+/// Lamports a third party escrowed, on top of rent, for whoever successfully challenges
+/// `wallet`'s request at `height` (see `add_challenge_incentive`).
+#[account]
+#[derive(InitSpace)]
+pub struct ChallengeIncentive {
+    pub funder: Pubkey,
+    pub wallet: Pubkey,
+    pub height: u64,
+    pub amount: u64,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+/// Marks an off-chain deposit as acknowledged by the node quorum (see `confirm_deposit`).
+#[account]
+#[derive(InitSpace)]
+pub struct DepositConfirmation {
+    pub deposit_ref: [u8; 32],
+    pub height: u64,
+    pub confirmed_at: i64,
+}
+
+/// Highest state height paid out for a wallet and token by `withdraw` or `instant_withdraw`;
+/// survives the request being closed.
+#[account]
+#[derive(InitSpace)]
+pub struct FinalizedHeight {
+    pub last_height: u64,
+}
+
+/// A signed ledger snapshot. Deliberately not `Default`: a zeroed state is never valid, so
+/// build one with `State::new` and check incoming ones with `State::validate`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct State {
+    /// Layout of the signed preimage; see `StateVersion` and `hash_state`.
+    pub state_version: u8,
     pub wallet: Pubkey,
     pub token: Pubkey,
     pub height: u64,
     pub balance: u64,
     pub participants: Vec<Pubkey>,
     pub sigs: Vec<Vec<u8>>,
+    /// Data-availability commitment for rollup-style deployments; all zeros when unused.
+    /// Only hashed when set, so states without one hash as they did before it existed.
+    pub da_hash: [u8; 32],
+    /// Unix timestamp after which the state can no longer be requested or challenged with;
+    /// `0` for none. Only hashed when set, like `da_hash`.
+    pub deadline: i64,
+}
+
+impl State {
+    pub fn new(
+        wallet: Pubkey,
+        token: Pubkey,
+        height: u64,
+        balance: u64,
+        participants: Vec<Pubkey>,
+        sigs: Vec<Vec<u8>>,
+    ) -> Self {
+        Self {
+            state_version: CURRENT_STATE_VERSION,
+            wallet,
+            token,
+            height,
+            balance,
+            participants,
+            sigs,
+            da_hash: [0; 32],
+            deadline: 0,
+        }
+    }
+
+    pub fn with_da_hash(mut self, da_hash: [u8; 32]) -> Self {
+        self.da_hash = da_hash;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: i64) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Rejects states with a zero wallet, token or height, or without participants — the shape
+    /// an uninitialized client-side state has — and states of an unknown `state_version`.
+    pub fn validate(&self) -> Result<()> {
+        StateVersion::try_from(self.state_version)?;
+        require!(
+            self.wallet != Pubkey::default()
+                && self.token != Pubkey::default()
+                && self.height > 0
+                && !self.participants.is_empty(),
+            ClearnetError::InvalidState
+        );
+        Ok(())
+    }
+}
+
+/// Known layouts of a `State`'s signed preimage. Older versions stay verifiable after the
+/// format changes, so states signed before an upgrade can still be used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StateVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+impl TryFrom<u8> for StateVersion {
+    type Error = Error;
+
+    fn try_from(version: u8) -> Result<Self> {
+        match version {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => err!(ClearnetError::UnsupportedStateVersion),
+        }
+    }
+}
+
+/// Steps reported by `RequestLifecycle::phase`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RequestPhase {
+    Requested = 0,
+    /// A newer state was proven against the request; always followed by `Rejected`.
+    Challenged = 1,
+    Rejected = 2,
+    Withdrawn = 3,
+    /// Closed without payout, e.g. swept after expiring unclaimed.
+    Cancelled = 4,
 }
 
 // --- Events ---
 
+#[event]
+pub struct ConfigMigrated {
+    pub from_version: u8,
+    pub to_version: u8,
+    pub ts: i64,
+}
+
+#[event]
+pub struct AdminChanged {
+    pub old: Pubkey,
+    pub new: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct FeeRecipientQueued {
+    pub fee_recipient: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct TotalReconcileQueued {
+    pub mint: Pubkey,
+    pub new_total: u64,
+    pub eta: i64,
+}
+
+#[event]
+pub struct TotalReconciled {
+    pub mint: Pubkey,
+    pub old: u64,
+    pub new: u64,
+}
+
+#[event]
+pub struct FeeRecipientChanged {
+    pub old: Pubkey,
+    pub new: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct NodeKeyRotated {
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+#[event]
+pub struct NodeWeightChanged {
+    pub node: Pubkey,
+    pub old_weight: u64,
+    pub new_weight: u64,
+}
+
+#[event]
+pub struct NodeBonded {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub bond: u64,
+}
+
+#[event]
+pub struct NodeBondEscrowed {
+    pub node: Pubkey,
+    pub amount: u64,
+    pub unlock_ts: i64,
+}
+
+#[event]
+pub struct NodeExited {
+    pub node: Pubkey,
+    /// Bond moved to the exit escrow.
+    pub bond: u64,
+    pub unlock_ts: i64,
+}
+
+#[event]
+pub struct NodeBondClaimed {
+    pub node: Pubkey,
+    pub amount: u64,
+}
+
+/// A node was deactivated, exited or slashed. Open requests aren't extended; watchers should
+/// challenge any whose quorum no longer holds.
+#[event]
+pub struct NodeSetChanged {
+    pub node: Pubkey,
+    /// Active nodes left after the change.
+    pub active_node_count: u32,
+    /// Requests open at the time, any of which may rely on `node`'s signature.
+    pub open_request_count: u32,
+    pub ts: i64,
+}
+
+#[event]
+pub struct NodeSlashed {
+    pub node: Pubkey,
+    pub reporter: Pubkey,
+    /// Bond and unlocked-in-time exit escrow taken from the node.
+    pub amount: u64,
+    /// Heights of the two conflicting states the node signed.
+    pub height_a: u64,
+    pub height_b: u64,
+    pub ts: i64,
+}
+
+/// How a slashed bond was split; the two amounts add up to `NodeSlashed.amount`.
+#[event]
+pub struct SlashDistributed {
+    pub node: Pubkey,
+    pub reporter: Pubkey,
+    pub reporter_amount: u64,
+    pub treasury_amount: u64,
+}
+
+#[event]
+pub struct VaultCreated {
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+}
+
+/// `mint` was added to or dropped from the `TokenRegistry`.
+#[event]
+pub struct TokenRegistryUpdated {
+    pub mint: Pubkey,
+    pub listed: bool,
+    pub ts: i64,
+}
+
 #[event]
 pub struct Deposited {
     pub wallet: Pubkey,
@@ -374,6 +5860,32 @@ pub struct Deposited {
     pub amount: u64,
 }
 
+/// A deposit with the vault totals it resulted in (see `set_deposit_accounting_events`).
+///
+/// There is no `new_user_balance`: the program keeps no per-user ledger (user balances live in
+/// the node-signed `State`s), so a wallet's balance can only be reconstructed off chain.
+#[event]
+pub struct DepositAccounted {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// `VaultState::total_deposited` after the deposit.
+    pub total_deposited: u64,
+    /// Tokens held by the vault token account after the deposit.
+    pub vault_balance: u64,
+    pub slot: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct DepositConfirmed {
+    pub deposit_ref: [u8; 32],
+    pub height: u64,
+    /// Total weight of the nodes whose signatures were counted.
+    pub signer_weight: u64,
+    pub ts: i64,
+}
+
 #[event]
 pub struct Requested {
     pub wallet: Pubkey,
@@ -386,6 +5898,8 @@ pub struct Challenged {
     pub wallet: Pubkey,
     pub height: u64,
     pub expiration: i64,
+    /// Challenge period applied to the request, after any tier for its amount.
+    pub challenge_period: i64,
 }
 
 #[event]
@@ -393,6 +5907,74 @@ pub struct Rejected {
     pub wallet: Pubkey,
     pub token: Pubkey,
     pub amount: u64,
+    /// Height of the state that won the challenge, for off-chain systems to converge on.
+    pub candidate_height: u64,
+}
+
+#[event]
+pub struct RequestSwept {
+    pub sweeper: Pubkey,
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+    pub amount: u64,
+    /// Lamports paid to the sweeper; the wallet got the rest of the request's rent and bounty.
+    pub reward: u64,
+}
+
+#[event]
+pub struct IncentiveAdded {
+    pub wallet: Pubkey,
+    pub height: u64,
+    pub funder: Pubkey,
+    pub amount: u64,
+    /// Escrowed by this funder for the request so far.
+    pub total: u64,
+}
+
+#[event]
+pub struct IncentivePaid {
+    pub wallet: Pubkey,
+    pub height: u64,
+    pub funder: Pubkey,
+    pub challenger: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RequestRevoked {
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+    pub amount: u64,
+    pub height: u64,
+}
+
+#[event]
+pub struct FeesClaimed {
+    pub mint: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct VaultMigrated {
+    pub mint: Pubkey,
+    pub new_vault: Pubkey,
+    pub amount: u64,
+    /// Deposits still owed to users, for the new custodian to take over.
+    pub total_deposited: u64,
+    pub ts: i64,
+}
+
+/// One event for every step of a request, for indexers that keep a single table. `phase` is a
+/// `RequestPhase`; `height` is the candidate's for `Challenged` and the request's otherwise.
+#[event]
+pub struct RequestLifecycle {
+    pub phase: u8,
+    pub wallet: Pubkey,
+    pub token: Pubkey,
+    pub amount: u64,
+    pub height: u64,
+    pub ts: i64,
 }
 
 #[event]
@@ -400,6 +5982,32 @@ pub struct Withdrawn {
     pub wallet: Pubkey,
     pub token: Pubkey,
     pub amount: u64,
+    pub fee: u64,
+    /// Lamports charged on top by `withdraw` (see `VaultConfig::sol_fee`).
+    pub sol_fee: u64,
+    /// Whether the wallet's `FeeExemption` waived `fee`.
+    pub fee_exempt: bool,
+    /// Token account paid `amount - fee`.
+    pub recipient: Pubkey,
+}
+
+/// `mint` was listed or unlisted as an instant token.
+#[event]
+pub struct InstantTokenListed {
+    pub mint: Pubkey,
+    pub listed: bool,
+    pub ts: i64,
+}
+
+/// A request of an instant token was paid out before its challenge period was over.
+#[event]
+pub struct InstantTokenWithdrawal {
+    pub wallet: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// When the skipped challenge period would have ended.
+    pub expiration: i64,
+    pub ts: i64,
 }
 
 // --- Errors ---
@@ -420,4 +6028,185 @@ pub enum ClearnetError {
     ChallengePeriodNotExpired,
     #[msg("State mismatch")]
     StateMismatch,
+    #[msg("Not enough admin signatures")]
+    InsufficientAdminSignatures,
+    #[msg("Invalid admin set")]
+    InvalidAdminSet,
+    #[msg("Mint does not match the requested token")]
+    MintMismatch,
+    #[msg("Invalid challenge period")]
+    InvalidChallengePeriod,
+    #[msg("Candidate at the same height does not prove fraud")]
+    NoFraudProven,
+    #[msg("Challenge period is below the minimum")]
+    ChallengePeriodTooShort,
+    #[msg("Invalid fee")]
+    InvalidFee,
+    #[msg("Fee account is not for the vault's mint")]
+    InvalidFeeAccount,
+    #[msg("Invalid dispute window")]
+    InvalidDisputeWindow,
+    #[msg("Invalid bond amount")]
+    InvalidBondAmount,
+    #[msg("Bond escrow account is required to deactivate a bonded node")]
+    MissingBondEscrow,
+    #[msg("Bond is still locked")]
+    BondLocked,
+    #[msg("States do not prove equivocation")]
+    NotEquivocation,
+    #[msg("Nothing to slash")]
+    NothingToSlash,
+    #[msg("The node still has a bond in escrow")]
+    BondEscrowPending,
+    #[msg("Instant withdrawals are disabled")]
+    InstantWithdrawDisabled,
+    #[msg("State height has already been finalized")]
+    HeightAlreadyFinalized,
+    #[msg("Participant is not an active node")]
+    UnauthorizedParticipant,
+    #[msg("Missing or invalid signature")]
+    InvalidSignature,
+    #[msg("Signatures must be 64 bytes")]
+    BadSignatureLength,
+    #[msg("Participant is not a valid Ed25519 public key")]
+    InvalidParticipantKey,
+    #[msg("Vault is paused")]
+    Paused,
+    #[msg("Token is paused")]
+    TokenPaused,
+    #[msg("Wallet is blacklisted")]
+    Blacklisted,
+    #[msg("Too many open withdrawal requests")]
+    TooManyOpenRequests,
+    #[msg("Node weight must be positive")]
+    InvalidNodeWeight,
+    #[msg("State has a zero wallet, token or height, or no participants")]
+    InvalidState,
+    #[msg("Heartbeat window must not be negative")]
+    InvalidHeartbeatWindow,
+    #[msg("Withdrawal amount is below the token minimum")]
+    WithdrawTooSmall,
+    #[msg("No vault has been created for this mint")]
+    VaultNotCreated,
+    #[msg("Node is not active")]
+    NodeNotActive,
+    #[msg("Unsupported state version")]
+    UnsupportedStateVersion,
+    #[msg("Wallet does not match the request")]
+    WalletMismatch,
+    #[msg("State has a zero balance")]
+    EmptyState,
+    #[msg("Sweep delay must not be negative")]
+    InvalidSweepDelay,
+    #[msg("Signer is not authorized for this instruction")]
+    Unauthorized,
+    #[msg("Request has not expired yet")]
+    RequestNotExpired,
+    #[msg("Admins may not be registered as nodes")]
+    AdminCannotBeNode,
+    #[msg("No fees have accrued")]
+    NoFeesToClaim,
+    #[msg("Expected an Ed25519Program instruction right before this one")]
+    MissingEd25519Instruction,
+    #[msg("Signatures do not reach the node quorum")]
+    InsufficientQuorum,
+    #[msg("remaining_accounts must be the participants' NodeEntry accounts, in order")]
+    ParticipantAccountMismatch,
+    #[msg("Relayer is not authorized by the user's signature")]
+    UnauthorizedRelayer,
+    #[msg("Token account is not the user's associated token account")]
+    NotAssociatedTokenAccount,
+    #[msg("Reactivation cooldown must not be negative")]
+    InvalidReactivationCooldown,
+    #[msg("Node was deactivated too recently to be reactivated")]
+    ReactivationCooldown,
+    #[msg("Token account has not approved the program's delegate")]
+    DelegateNotApproved,
+    #[msg("Delegated amount does not cover the deposit")]
+    InsufficientDelegation,
+    #[msg("Max participants must be between 1 and MAX_PARTICIPANTS")]
+    InvalidMaxParticipants,
+    #[msg("State has more participants than allowed")]
+    TooManyParticipants,
+    #[msg("Vault holds less than its liabilities")]
+    VaultInsolvent,
+    #[msg("Deposit has already been confirmed")]
+    DepositAlreadyConfirmed,
+    #[msg(
+        "Challenge tiers must be at most MAX_CHALLENGE_TIERS with ascending non-zero thresholds"
+    )]
+    InvalidChallengeTiers,
+    #[msg("Vault can only be migrated while paused")]
+    NotPaused,
+    #[msg("Vault cannot be migrated to itself")]
+    InvalidVaultMigration,
+    #[msg("Node already has this status")]
+    NoStatusChange,
+    #[msg("Withdrawal amount above the token's single-withdrawal cap")]
+    WithdrawTooLarge,
+    #[msg("Fewer distinct signers than min_signers")]
+    TooFewSigners,
+    #[msg("min_signers exceeds the active node set or max_participants")]
+    InvalidMinSigners,
+    // No longer returned since set_fee dropped its fee_recipient; kept so later codes don't shift.
+    #[msg("Fee recipient changes go through queue_fee_recipient")]
+    FeeRecipientTimelocked,
+    #[msg("Timelock delay must be at least MIN_TIMELOCK_DELAY")]
+    InvalidTimelockDelay,
+    #[msg("No change is queued")]
+    NoQueuedChange,
+    #[msg("Timelock has not expired yet")]
+    TimelockNotExpired,
+    #[msg("Too many candidates; at most MAX_CHALLENGE_CANDIDATES")]
+    TooManyCandidates,
+    #[msg("No candidate is validly signed and supersedes the request")]
+    NoValidCandidate,
+    #[msg("Slash split must be at most 10000 basis points")]
+    InvalidSlashSplit,
+    #[msg("The token registry is full")]
+    TooManyTokens,
+    #[msg("The request can no longer be challenged")]
+    ChallengeWindowClosed,
+    #[msg("Challenge lockout must be non-negative and below the minimum challenge period")]
+    InvalidChallengeLockout,
+    #[msg("Reclaim the incentive left from an earlier request first")]
+    StaleIncentive,
+    #[msg("The incentive's request is still pending")]
+    IncentiveLocked,
+    #[msg("Deposit amount is below the minimum")]
+    DepositTooSmall,
+    #[msg("Reconciled total exceeds the vault balance")]
+    ReconcileExceedsBalance,
+    #[msg("Total active node weight overflows")]
+    WeightOverflow,
+    #[msg("Node would hold more than max_single_weight_bps of the active weight")]
+    WeightTooConcentrated,
+    #[msg("Weight cap cannot exceed 10000 bps")]
+    InvalidWeightCap,
+    #[msg("Split portions must add up to the requested amount, one token account each")]
+    SplitMismatch,
+    #[msg("Too many split recipients")]
+    TooManySplitRecipients,
+    #[msg("Account would drop below the rent-exempt minimum")]
+    RentExemptViolation,
+    #[msg("State deadline is further out than max_state_lifetime")]
+    DeadlineTooFar,
+    #[msg("State deadline has passed")]
+    StateExpired,
+    #[msg("max_state_lifetime cannot be negative")]
+    InvalidStateLifetime,
+    #[msg("activation_grace cannot be negative")]
+    InvalidActivationGrace,
+    #[msg("The token is already registered")]
+    TokenAlreadyRegistered,
+    #[msg("The token is not registered")]
+    TokenNotRegistered,
+    #[msg("Node has sent a heartbeat within the heartbeat window")]
+    NodeNotStale,
+    #[msg("Deposit amount is above max_deposit")]
+    DepositTooLarge,
+    #[msg("Deposit would exceed the vault's deposit cap")]
+    DepositCapExceeded,
+    #[msg("Withdrawal would exceed today's withdraw limit")]
+    DailyWithdrawLimitExceeded,
 }